@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+use crate::{eval::Features, gamestate::Lock, movegen::Placement, replay::{Playback, Replay}, tetromino::Shape, util::Orientation};
+
+/// the shapes in the order they are listed
+pub const SHAPES: [Shape; 7] = [Shape::O, Shape::I, Shape::L, Shape::J, Shape::S, Shape::Z, Shape::T];
+const ORIENTATIONS: [Orientation; 4] = [Orientation::North, Orientation::East, Orientation::South, Orientation::West];
+
+/// One placement of a recorded game and what it did to the stack.
+#[derive(Clone, Copy)]
+pub struct Record {
+    /// index of the replay among those analysed
+    pub game: usize,
+    /// frame the piece locked in
+    pub frame: usize,
+    pub shape: Shape,
+    pub orientation: Orientation,
+    /// stack height after the placement and its line clears
+    pub height: usize,
+    /// holes in the stack after the placement
+    pub holes: usize,
+    /// holes the placement added, none if it opened up more than it covered
+    pub holes_created: usize,
+}
+
+/// Where and how pieces were placed over a number of recorded games.
+#[derive(Default)]
+pub struct Analytics {
+    /// every placement, game by game in the order they were made
+    pub records: Vec<Record>,
+    /// cells covered by the placements of each shape, by grid row and column
+    pub heatmap: HashMap<Shape, [[usize; 10]; 22]>,
+    pub games: usize,
+}
+
+impl Analytics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Plays a replay through and adds up every placement made in it.
+    pub fn add_replay(&mut self, replay: &Replay) {
+        let mut playback = Playback::new(replay.clone());
+        let mut pieces = playback.gamestate.pieces;
+
+        while !playback.finished() {
+            playback.step();
+
+            if playback.gamestate.pieces > pieces {
+                pieces = playback.gamestate.pieces;
+                if let Some(lock) = playback.gamestate.last_lock.as_ref() { self.add_lock(playback.gamestate.ticks, lock) }
+            }
+        }
+
+        self.games += 1;
+    }
+
+    /// Adds a placement of the current game.
+    pub fn add_lock(&mut self, frame: usize, lock: &Lock) {
+        let placement = Placement { tetromino: lock.tetromino, pos: lock.pos, inputs: Vec::new() };
+        let heatmap = self.heatmap.entry(lock.tetromino.shape).or_insert([[0; 10]; 22]);
+        for (y, x) in placement.cells() {
+            heatmap[y][x] += 1;
+        }
+
+        let before = Features::from_grid(&lock.grid);
+        let mut grid = lock.grid;
+        placement.place(&mut grid);
+        let after = Features::from_grid(&grid);
+
+        self.records.push(Record {
+            game: self.games,
+            frame,
+            shape: lock.tetromino.shape,
+            orientation: lock.tetromino.orientation,
+            height: after.max_height,
+            holes: after.holes,
+            holes_created: after.holes.saturating_sub(before.holes),
+        });
+    }
+
+    /// the placements of one shape, or of all with `None`
+    fn get_records(&self, shape: Option<Shape>) -> impl Iterator<Item = &Record> {
+        self.records.iter().filter(move |record| shape.is_none_or(|shape| record.shape == shape))
+    }
+
+    /// cells covered by the placements of one shape, or of all with `None`
+    pub fn cells(&self, shape: Option<Shape>) -> [[usize; 10]; 22] {
+        let mut cells = [[0; 10]; 22];
+        for (_, heatmap) in self.heatmap.iter().filter(|&(&other, _)| shape.is_none_or(|shape| other == shape)) {
+            for (y, row) in heatmap.iter().enumerate() {
+                for (x, count) in row.iter().enumerate() {
+                    cells[y][x] += count;
+                }
+            }
+        }
+        cells
+    }
+
+    /// how often each orientation was used, by one shape or all with `None`
+    pub fn orientations(&self, shape: Option<Shape>) -> [(Orientation, usize); 4] {
+        ORIENTATIONS.map(|orientation| (orientation, self.get_records(shape).filter(|record| record.orientation == orientation).count()))
+    }
+
+    /// share of the placements that added holes, by one shape or all with `None`
+    pub fn hole_rate(&self, shape: Option<Shape>) -> f64 {
+        let (placements, holing) = self.get_records(shape)
+            .fold((0, 0), |(placements, holing), record| (placements + 1, holing + (record.holes_created > 0) as usize));
+        if placements == 0 { return 0.0 }
+        holing as f64 / placements as f64
+    }
+
+    /// Average stack height over `parts` equal stretches of every game, from its start to its end.
+    pub fn heights(&self, parts: usize) -> Vec<f64> {
+        let mut sums = vec![(0, 0); parts];
+
+        for game in 0..self.games {
+            let records: Vec<&Record> = self.records.iter().filter(|record| record.game == game).collect();
+            for (i, record) in records.iter().enumerate() {
+                let (sum, count) = &mut sums[i * parts / records.len()];
+                *sum += record.height;
+                *count += 1;
+            }
+        }
+
+        sums.into_iter().map(|(sum, count)| if count == 0 { 0.0 } else { sum as f64 / count as f64 }).collect()
+    }
+
+    /// Writes `<prefix>-placements.csv` with every placement, `<prefix>-heatmap.csv` with the cells covered by each shape
+    /// and `<prefix>-orientations.csv` with how often each shape was placed in each orientation.
+    pub fn save_csv(&self, prefix: &str) -> Result<(), String> {
+        let mut placements = "game,frame,shape,orientation,height,holes,holes_created\n".to_string();
+        for record in &self.records {
+            placements.push_str(&format!("{},{},{},{},{},{},{}\n",
+                record.game, record.frame, record.shape.to_char(), record.orientation, record.height, record.holes, record.holes_created,
+            ));
+        }
+
+        // rows are counted from the floor up, the way players read the stack
+        let mut heatmap = "shape,row,column,cells\n".to_string();
+        for shape in SHAPES {
+            let cells = self.cells(Some(shape));
+            for (y, row) in cells.iter().enumerate().rev() {
+                for (x, count) in row.iter().enumerate().filter(|&(_, &count)| count > 0) {
+                    heatmap.push_str(&format!("{},{},{},{}\n", shape.to_char(), 21 - y, x, count));
+                }
+            }
+        }
+
+        let mut orientations = "shape,orientation,placements\n".to_string();
+        for shape in SHAPES {
+            for (orientation, count) in self.orientations(Some(shape)) {
+                orientations.push_str(&format!("{},{},{}\n", shape.to_char(), orientation, count));
+            }
+        }
+
+        for (name, text) in [("placements", placements), ("heatmap", heatmap), ("orientations", orientations)] {
+            let path = format!("{}-{}.csv", prefix, name);
+            std::fs::write(&path, text).map_err(|e| format!("{}: {}", path, e))?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,147 @@
+//! Evolves the bot's evaluation weights with a genetic algorithm, playing seeded games without a window.
+//!
+//! `train [--generations=N] [--population=N] [--games=N] [--pieces=N] [--threads=N] [--seed=N] [--weights=<file>] [--out=<file>]`
+//!
+//! Every generation plays the same fresh seeds for all individuals, the fitness is the average score.
+//! The best weights so far play the new seeds as well, and are only replaced by an individual scoring higher on them.
+//! They are written to the output file after every generation, so the run can be stopped any time.
+
+use std::sync::{atomic::{AtomicUsize, Ordering}, Mutex};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use tetris::{bot::Bot, eval::Weights, gamestate::{GameState, Mode, State, Visibility}, randomizer::Randomizer, FPS};
+
+/// best individuals carried over unchanged into the next generation
+const ELITE: usize = 2;
+const TOURNAMENT_SIZE: usize = 3;
+/// chance of every weight to change when mutating
+const MUTATION_RATE: f64 = 0.3;
+/// largest change of a weight when mutating
+const MUTATION_SIZE: f64 = 0.2;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let generations = get_arg(&args, "--generations=", 50);
+    let population_size: usize = get_arg(&args, "--population=", 32).max(ELITE + 1);
+    let games: usize = get_arg(&args, "--games=", 4).max(1);
+    let pieces = get_arg(&args, "--pieces=", 500);
+    let threads: usize = get_arg(&args, "--threads=", std::thread::available_parallelism().map_or(1, |threads| threads.get())).max(1);
+    let seed = get_arg(&args, "--seed=", 1);
+    let out = args.iter().find_map(|arg| arg.strip_prefix("--out=")).unwrap_or("weights.txt");
+
+    let start = match args.iter().find_map(|arg| arg.strip_prefix("--weights=")) {
+        Some(path) => match Weights::load(path) {
+            Ok(weights) => weights,
+            Err(e) => { eprintln!("{}", e); return },
+        },
+        None => Weights::default(),
+    };
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut population = vec![start];
+    while population.len() < population_size {
+        population.push(mutate(start, &mut rng));
+    }
+
+    let mut best: Option<(f64, Weights)> = None;
+
+    for generation in 0..generations {
+        let game_seed = seed.wrapping_add((generation * games) as u64);
+
+        // the best so far plays the same seeds again, a fitness from other seeds is no measure
+        let mut candidates = population.clone();
+        if let Some((_, weights)) = best { candidates.push(weights) }
+        let mut fitness = get_fitness(&candidates, games, pieces, threads, game_seed);
+        if let Some((_, weights)) = best { best = fitness.pop().map(|fitness| (fitness, weights)) }
+
+        let mut ranked: Vec<(f64, Weights)> = fitness.into_iter().zip(population).collect();
+        ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let mean = ranked.iter().map(|(fitness, _)| fitness).sum::<f64>() / ranked.len() as f64;
+        println!("generation {} best {:.0} mean {:.0}", generation + 1, ranked[0].0, mean);
+
+        if best.is_none_or(|(fitness, _)| ranked[0].0 > fitness) {
+            best = Some(ranked[0]);
+            if let Err(e) = ranked[0].1.save(out) { eprintln!("{}", e); return }
+        }
+
+        population = ranked.iter().take(ELITE).map(|&(_, weights)| weights).collect();
+        while population.len() < population_size {
+            let child = crossover(select(&ranked, &mut rng), select(&ranked, &mut rng), &mut rng);
+            population.push(mutate(child, &mut rng));
+        }
+    }
+
+    if let Some((fitness, _)) = best {
+        println!("best {:.0} written to {}", fitness, out);
+    }
+}
+
+fn get_arg<T: std::str::FromStr>(args: &[String], name: &str, default: T) -> T {
+    args.iter()
+        .find_map(|arg| arg.strip_prefix(name))
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Average score of every individual over `games` games, played on `threads` threads.
+fn get_fitness(population: &[Weights], games: usize, pieces: usize, threads: usize, seed: u64) -> Vec<f64> {
+    let next = AtomicUsize::new(0);
+    let fitness = Mutex::new(vec![0.0; population.len()]);
+
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                if index >= population.len() { break }
+
+                let total: usize = (0..games).map(|game| play(population[index], seed.wrapping_add(game as u64), pieces)).sum();
+                fitness.lock().unwrap()[index] = total as f64 / games as f64;
+            });
+        }
+    });
+
+    fitness.into_inner().unwrap()
+}
+
+/// Lets the bot play a marathon game for at most `pieces` pieces, returns the score.
+fn play(weights: Weights, seed: u64, pieces: usize) -> usize {
+    let mut g = GameState::new(Mode::Marathon, Visibility::Normal);
+    g.randomizer = Randomizer::new(seed);
+
+    // placing on every frame it can, the lock delay is the only limit
+    let mut bot = Bot::new(weights, 0, FPS as f64);
+
+    while g.state == State::Running && g.pieces < pieces {
+        g.tick();
+        for action in bot.actions(&g) {
+            g.apply(action);
+        }
+    }
+
+    g.score
+}
+
+/// the best of a few random individuals
+fn select(ranked: &[(f64, Weights)], rng: &mut StdRng) -> Weights {
+    (0..TOURNAMENT_SIZE)
+        .map(|_| ranked[rng.gen_range(0..ranked.len())])
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .unwrap()
+        .1
+}
+
+/// every weight taken from either parent
+fn crossover(a: Weights, b: Weights, rng: &mut StdRng) -> Weights {
+    let (a, b) = (a.values(), b.values());
+    Weights::from_values(std::array::from_fn(|i| if rng.gen_bool(0.5) { a[i] } else { b[i] }))
+}
+
+fn mutate(weights: Weights, rng: &mut StdRng) -> Weights {
+    let mut values = weights.values();
+    for value in values.iter_mut() {
+        if rng.gen_bool(MUTATION_RATE) { *value += rng.gen_range(-MUTATION_SIZE..MUTATION_SIZE) }
+    }
+    Weights::from_values(values)
+}
@@ -0,0 +1,90 @@
+use crate::{gamestate::{Cell, GameState}, tetromino::{Color, Shape}};
+
+/// A starting position: the grid and a fixed queue of pieces that are dealt before the regular bag.
+///
+/// The text format is a `queue` line listing the pieces followed by up to 22 rows of 10 cells,
+/// aligned to the bottom of the grid. `.` is empty, `IOTSZLJ` are colored like their pieces and
+/// `G` is garbage. Lines starting with `#` are comments.
+#[derive(Clone, Default)]
+pub struct Board {
+    pub grid: [[Option<Color>; 10]; 22],
+    pub queue: Vec<Shape>,
+}
+
+impl Board {
+    pub fn new() -> Self {
+        Self { grid: [[None; 10]; 22], queue: Vec::new() }
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_string()).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut board = Self::new();
+        let mut rows = Vec::new();
+
+        for line in text.lines().map(|line| line.trim()) {
+            if line.is_empty() || line.starts_with('#') { continue }
+
+            if let Some(queue) = line.strip_prefix("queue") {
+                board.queue = parse_queue(queue.trim())?;
+                continue;
+            }
+
+            let row = line.chars().map(|c| match c {
+                '.' => Ok(None),
+                'G' => Ok(Some(Color::Gray)),
+                c => Shape::from_char(c).map(|shape| Some(shape.color())).ok_or(format!("invalid cell '{}'", c)),
+            }).collect::<Result<Vec<Option<Color>>, String>>()?;
+
+            if row.len() != 10 { return Err(format!("row '{}' is not 10 cells wide", line)) }
+            rows.push(row);
+        }
+
+        if rows.len() > 22 { return Err(format!("{} rows do not fit into the grid", rows.len())) }
+
+        for (y, row) in (22 - rows.len()..22).zip(rows) {
+            board.grid[y].copy_from_slice(&row);
+        }
+
+        Ok(board)
+    }
+
+    /// Sets up the grid and queue of `gamestate`, the regular bag continues once the queue ran out.
+    pub fn apply(&self, gamestate: &mut GameState) {
+        gamestate.grid = self.grid.map(|line| line.map(|cell| cell.map(|color| Cell { color, locked_at: gamestate.ticks })));
+        gamestate.next = self.queue.iter().rev().copied().collect();
+    }
+}
+
+impl std::fmt::Display for Board {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "queue {}", self.queue.iter().map(|shape| shape.to_char()).collect::<String>())?;
+
+        // empty rows above the stack are left out
+        let top = self.grid.iter().position(|line| line.iter().any(|cell| cell.is_some())).unwrap_or(22);
+
+        for line in self.grid[top..].iter() {
+            writeln!(f, "{}", line.iter().map(|cell| match cell {
+                None => '.',
+                Some(Color::Gray) => 'G',
+                Some(color) => Shape::from_color(*color).unwrap().to_char(),
+            }).collect::<String>())?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn parse_queue(text: &str) -> Result<Vec<Shape>, String> {
+    text.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| Shape::from_char(c).ok_or(format!("invalid piece '{}'", c)))
+        .collect()
+}
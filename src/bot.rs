@@ -0,0 +1,135 @@
+use crate::{eval::{Features, Weights}, gamestate::GameState, movegen::{self, Placement}, tetromino::Shape, util::Action};
+
+/// positions kept at every depth of the lookahead
+const BEAM_WIDTH: usize = 16;
+
+/// A player searching placements of the current piece and the preview queue.
+pub struct Bot {
+    pub weights: Weights,
+    /// pieces of the queue looked at after the current one
+    pub lookahead: usize,
+    /// pieces placed per second at most, the lock delay limits it as well
+    pub pps: f64,
+    /// time since the last placement, in placements
+    progress: f64,
+    /// number of locked pieces when the bot last placed, so every piece is placed once
+    placed: Option<usize>,
+}
+
+/// a position in the search, with the inputs for the current piece leading to it
+struct Node {
+    gamestate: GameState,
+    hold: Option<Shape>,
+    /// index of the next piece in the queue
+    index: usize,
+    /// accumulated for line clears on the way
+    reward: f64,
+    value: f64,
+    inputs: Vec<Action>,
+}
+
+impl Bot {
+    pub fn new(weights: Weights, lookahead: usize, pps: f64) -> Self {
+        Self { weights, lookahead, pps, progress: 0.0, placed: None }
+    }
+
+    /// The inputs to apply this frame: all inputs of a placement at once when it is time for one.
+    pub fn actions(&mut self, gamestate: &GameState) -> Vec<Action> {
+        self.progress = (self.progress + self.pps / crate::FPS as f64).min(1.0);
+
+        if self.progress < 1.0 || gamestate.current.is_none() || self.placed == Some(gamestate.pieces) { return Vec::new() }
+
+        self.progress -= 1.0;
+        self.placed = Some(gamestate.pieces);
+        self.plan(gamestate)
+    }
+
+    /// Searches the best placement for the current piece, looking `lookahead` pieces ahead.
+    pub fn plan(&self, gamestate: &GameState) -> Vec<Action> {
+        let (tetro, pos) = match gamestate.current {
+            Some(current) => current,
+            None => return Vec::new(),
+        };
+
+        let queue: Vec<Shape> = std::iter::once(tetro.shape).chain(gamestate.next.iter().rev().copied()).collect();
+        let root = Node { gamestate: gamestate.clone(), hold: gamestate.hold, index: 0, reward: 0.0, value: 0.0, inputs: Vec::new() };
+
+        // the current piece may have moved already, a piece coming out of hold starts at the spawn
+        let mut beam = Vec::new();
+        for placement in movegen::placements_from(gamestate, tetro, pos) {
+            beam.extend(self.child(&root, &placement, root.hold, 1, placement.inputs.clone()));
+        }
+        if !gamestate.hold_used {
+            let (shape, index) = match gamestate.hold {
+                Some(shape) => (Some(shape), 1),
+                None => (queue.get(1).copied(), 2),
+            };
+            if let Some(shape) = shape {
+                for placement in movegen::placements(gamestate, shape) {
+                    let inputs = std::iter::once(Action::Hold).chain(placement.inputs.iter().copied()).collect();
+                    beam.extend(self.child(&root, &placement, Some(tetro.shape), index, inputs));
+                }
+            }
+        }
+        prune(&mut beam);
+
+        for _ in 0..self.lookahead {
+            let mut children = Vec::new();
+            for node in beam.iter() {
+                children.extend(self.expand(node, &queue));
+            }
+            if children.is_empty() { break }
+
+            beam = children;
+            prune(&mut beam);
+        }
+
+        match beam.into_iter().next() {
+            Some(node) => node.inputs.into_iter().chain(std::iter::once(Action::HardDrop)).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// positions after placing the next queued piece, or the held one instead
+    fn expand(&self, node: &Node, queue: &[Shape]) -> Vec<Node> {
+        let mut children = Vec::new();
+
+        let shape = match queue.get(node.index) {
+            Some(&shape) => shape,
+            None => return children,
+        };
+
+        for placement in movegen::placements(&node.gamestate, shape) {
+            children.extend(self.child(node, &placement, node.hold, node.index + 1, node.inputs.clone()));
+        }
+
+        let (held, index) = match node.hold {
+            Some(held) => (Some(held), node.index + 1),
+            None => (queue.get(node.index + 1).copied(), node.index + 2),
+        };
+        if let Some(held) = held.filter(|&held| held != shape) {
+            for placement in movegen::placements(&node.gamestate, held) {
+                children.extend(self.child(node, &placement, Some(shape), index, node.inputs.clone()));
+            }
+        }
+
+        children
+    }
+
+    /// the position after `placement`, `None` if it tops out
+    fn child(&self, node: &Node, placement: &Placement, hold: Option<Shape>, index: usize, inputs: Vec<Action>) -> Option<Node> {
+        let mut gamestate = node.gamestate.clone();
+        let lines = placement.place(&mut gamestate.grid);
+        if gamestate.grid[..2].iter().flatten().any(|cell| cell.is_some()) { return None }
+
+        let reward = node.reward + self.weights.clear(lines);
+        let value = reward + self.weights.evaluate(&Features::from_grid(&gamestate.grid));
+        Some(Node { gamestate, hold, index, reward, value, inputs })
+    }
+}
+
+/// keeps the best `BEAM_WIDTH` nodes, best first
+fn prune(beam: &mut Vec<Node>) {
+    beam.sort_by(|a, b| b.value.total_cmp(&a.value));
+    beam.truncate(BEAM_WIDTH);
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use rand::seq::SliceRandom;
+
+use crate::{finesse::{self, Check}, gamestate::GameState, movegen::{self, Placement}, tetromino::Shape, util::Orientation};
+
+/// Attempts at targets of one shape, orientation and leftmost column.
+#[derive(Clone, Copy, Default)]
+pub struct Stats {
+    pub attempts: usize,
+    /// placed on the target with the fewest inputs
+    pub correct: usize,
+    /// frames from the target showing up to the lock, summed over all attempts
+    pub frames: usize,
+}
+
+/// How the last piece did against its target.
+#[derive(Clone, Copy)]
+pub struct Attempt {
+    /// whether the piece locked on the target cells
+    pub placed: bool,
+    pub check: Option<Check>,
+    pub frames: usize,
+}
+
+impl Attempt {
+    pub fn is_correct(&self) -> bool {
+        self.placed && self.check.is_some_and(|check| !check.is_fault())
+    }
+}
+
+/// Finesse practice, every piece gets a random placement to reach with the fewest inputs.
+///
+/// The field is cleared after every lock, so each target is placed on an empty board.
+#[derive(Default)]
+pub struct Drill {
+    /// the placement to reach with the current piece
+    pub target: Option<Placement>,
+    pub last: Option<Attempt>,
+    pub stats: HashMap<(Shape, Orientation, usize), Stats>,
+    /// frame the target was set in
+    started: usize,
+    /// pieces already scored
+    pieces: usize,
+}
+
+impl Drill {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Scores a newly locked piece and sets the next target, to be called once per frame after the game advanced.
+    pub fn update(&mut self, gamestate: &mut GameState) {
+        if gamestate.pieces > self.pieces {
+            self.pieces = gamestate.pieces;
+
+            if let (Some(target), Some(lock)) = (self.target.take(), gamestate.last_lock.as_ref()) {
+                let locked = Placement { tetromino: lock.tetromino, pos: lock.pos, inputs: Vec::new() };
+                let attempt = Attempt {
+                    placed: locked.cells() == target.cells(),
+                    check: finesse::check(lock),
+                    frames: gamestate.ticks - self.started,
+                };
+
+                let stats = self.stats.entry(get_key(&target)).or_default();
+                stats.attempts += 1;
+                stats.correct += attempt.is_correct() as usize;
+                stats.frames += attempt.frames;
+                self.last = Some(attempt);
+            }
+
+            gamestate.grid = [[None; 10]; 22];
+        }
+
+        // a new piece, or a different one out of hold
+        let shape = match gamestate.current {
+            Some((tetro, _)) => tetro.shape,
+            None => return,
+        };
+        if self.target.as_ref().is_some_and(|target| target.tetromino.shape == shape) { return }
+
+        self.target = movegen::placements(gamestate, shape).choose(&mut rand::thread_rng()).cloned();
+        self.started = gamestate.ticks;
+    }
+
+    /// all attempts summed up
+    pub fn total(&self) -> Stats {
+        self.stats.values().fold(Stats::default(), |total, stats| Stats {
+            attempts: total.attempts + stats.attempts,
+            correct: total.correct + stats.correct,
+            frames: total.frames + stats.frames,
+        })
+    }
+}
+
+/// shape, orientation and leftmost column of a placement
+fn get_key(placement: &Placement) -> (Shape, Orientation, usize) {
+    let column = placement.cells().iter().map(|&(_, x)| x).min().unwrap();
+    (placement.tetromino.shape, placement.tetromino.orientation, column)
+}
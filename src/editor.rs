@@ -0,0 +1,58 @@
+use tetris::{board::Board, gamestate::{GameState, Mode, Visibility}, tetromino::{Color, Shape}, util::{Event, Keycode, MouseButton}};
+
+use crate::rendering::Renderer;
+
+const PAINT_COLORS: [Color; 8] = [Color::Gray, Color::Teal, Color::Blue, Color::Orange, Color::Yellow, Color::Green, Color::Purple, Color::Red];
+
+/// Lets the player paint `board` with the mouse and type its piece queue.
+/// Returns `false` if the player quit instead of confirming the board with enter.
+pub fn run(renderer: &mut dyn Renderer, board: &mut Board) -> bool {
+    let mut g = GameState::new(Mode::Zen { gravity: 0 }, Visibility::Normal);
+    let mut paint = 0;
+
+    loop {
+        for event in renderer.get_events() {
+            match event {
+                Event::Quit => { renderer.set_overlay(Vec::new()); return false },
+                Event::KeyDown(Keycode::Return) => { renderer.set_overlay(Vec::new()); return true },
+                Event::Mouse(MouseButton::Left, cell) => board.grid[cell.y][cell.x] = Some(PAINT_COLORS[paint]),
+                Event::Mouse(MouseButton::Right, cell) => board.grid[cell.y][cell.x] = None,
+                Event::KeyDown(Keycode::Tab) => paint = (paint + 1) % PAINT_COLORS.len(),
+                Event::KeyDown(Keycode::Backspace) => { board.queue.pop(); },
+                Event::KeyDown(key) => {
+                    if let Some(shape) = get_key_shape(key) { board.queue.push(shape) }
+                },
+                _ => (),
+            }
+        }
+
+        board.apply(&mut g);
+
+        let paint_name = match Shape::from_color(PAINT_COLORS[paint]) {
+            Some(shape) => shape.to_char().to_string(),
+            None => "garbage".to_string(),
+        };
+        renderer.set_overlay(vec![
+            format!("queue: {}", board.queue.iter().map(|shape| shape.to_char()).collect::<String>()),
+            format!("paint: {} (tab)", paint_name),
+            "left paint, right erase".to_string(),
+            "enter to play".to_string(),
+        ]);
+        renderer.draw(&mut g);
+
+        std::thread::sleep(std::time::Duration::from_millis(crate::MILLISPF));
+    }
+}
+
+fn get_key_shape(key: Keycode) -> Option<Shape> {
+    match key {
+        Keycode::I => Some(Shape::I),
+        Keycode::O => Some(Shape::O),
+        Keycode::T => Some(Shape::T),
+        Keycode::S => Some(Shape::S),
+        Keycode::Z => Some(Shape::Z),
+        Keycode::L => Some(Shape::L),
+        Keycode::J => Some(Shape::J),
+        _ => None,
+    }
+}
@@ -0,0 +1,204 @@
+use crate::gamestate::Cell;
+
+/// Measurements of a grid that tell how well it can be played on.
+#[derive(Clone, Copy)]
+pub struct Features {
+    /// summed column heights
+    pub height: usize,
+    pub max_height: usize,
+    /// empty cells with a filled cell somewhere above them
+    pub holes: usize,
+    /// changes between empty and filled along each row, the walls count as filled
+    pub row_transitions: usize,
+    /// changes between empty and filled down each column, the floor counts as filled
+    pub column_transitions: usize,
+    /// summed depth of columns lower than both neighbours
+    pub wells: usize,
+    /// summed height differences of neighbouring columns
+    pub bumpiness: usize,
+    /// open slots a T piece can spin into for a double
+    pub tspin_slots: usize,
+}
+
+impl Features {
+    pub fn from_grid(grid: &[[Option<Cell>; 10]; 22]) -> Self {
+        let filled = |x: usize, y: usize| grid[y][x].is_some();
+
+        let heights: Vec<usize> = (0..10)
+            .map(|x| (0..22).find(|&y| filled(x, y)).map_or(0, |y| 22 - y))
+            .collect();
+
+        let holes = (0..10)
+            .map(|x| (22 - heights[x]..22).filter(|&y| !filled(x, y)).count())
+            .sum();
+
+        let row_transitions = (0..22)
+            .filter(|&y| grid[y].iter().any(|cell| cell.is_some()))
+            .map(|y| (0..=10).filter(|&x| (x == 0 || filled(x - 1, y)) != (x == 10 || filled(x, y))).count())
+            .sum();
+
+        let column_transitions = (0..10)
+            .map(|x| (22 - heights[x]..22).filter(|&y| filled(x, y) != (y == 21 || filled(x, y + 1))).count())
+            .sum();
+
+        let wells = (0..10)
+            .map(|x| {
+                let left = if x == 0 { 22 } else { heights[x - 1] };
+                let right = if x == 9 { 22 } else { heights[x + 1] };
+                left.min(right).saturating_sub(heights[x])
+            })
+            .sum();
+
+        Self {
+            height: heights.iter().sum(),
+            max_height: heights.iter().copied().max().unwrap_or(0),
+            holes,
+            row_transitions,
+            column_transitions,
+            wells,
+            bumpiness: heights.windows(2).map(|pair| pair[0].abs_diff(pair[1])).sum(),
+            tspin_slots: count_tspin_slots(grid),
+        }
+    }
+}
+
+/// names of the weights in files, in the order of `Weights::values`
+pub const WEIGHT_NAMES: [&str; 10] = [
+    "height",
+    "max_height",
+    "holes",
+    "row_transitions",
+    "column_transitions",
+    "wells",
+    "bumpiness",
+    "tspin_slots",
+    "lines",
+    "tetris",
+];
+
+/// How much each feature and line clear counts, positive is good.
+///
+/// The file format is one `name value` pair per line, named like the fields.
+/// Missing names keep their default and lines starting with `#` are comments.
+#[derive(Clone, Copy)]
+pub struct Weights {
+    pub height: f64,
+    pub max_height: f64,
+    pub holes: f64,
+    pub row_transitions: f64,
+    pub column_transitions: f64,
+    pub wells: f64,
+    pub bumpiness: f64,
+    pub tspin_slots: f64,
+    /// per cleared line
+    pub lines: f64,
+    /// on top of `lines` for clearing four at once
+    pub tetris: f64,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            height: -0.51,
+            max_height: 0.0,
+            holes: -0.36,
+            row_transitions: 0.0,
+            column_transitions: 0.0,
+            wells: -0.05,
+            bumpiness: -0.18,
+            tspin_slots: 0.3,
+            lines: 0.76,
+            tetris: 2.0,
+        }
+    }
+}
+
+impl Weights {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_string()).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut values = Self::default().values();
+
+        for line in text.lines().map(|line| line.trim()) {
+            if line.is_empty() || line.starts_with('#') { continue }
+
+            let (name, value) = line.split_once(char::is_whitespace).ok_or(format!("missing value in '{}'", line))?;
+            let index = WEIGHT_NAMES.iter().position(|&known| known == name).ok_or(format!("unknown weight '{}'", name))?;
+            values[index] = value.trim().parse().map_err(|_| format!("invalid value in '{}'", line))?;
+        }
+
+        Ok(Self::from_values(values))
+    }
+
+    /// all weights in the order of `WEIGHT_NAMES`
+    pub fn values(&self) -> [f64; 10] {
+        [
+            self.height,
+            self.max_height,
+            self.holes,
+            self.row_transitions,
+            self.column_transitions,
+            self.wells,
+            self.bumpiness,
+            self.tspin_slots,
+            self.lines,
+            self.tetris,
+        ]
+    }
+
+    pub fn from_values(values: [f64; 10]) -> Self {
+        let [height, max_height, holes, row_transitions, column_transitions, wells, bumpiness, tspin_slots, lines, tetris] = values;
+        Self { height, max_height, holes, row_transitions, column_transitions, wells, bumpiness, tspin_slots, lines, tetris }
+    }
+
+    /// How good a position with `features` looks to continue from, higher is better.
+    pub fn evaluate(&self, features: &Features) -> f64 {
+        self.height * features.height as f64
+            + self.max_height * features.max_height as f64
+            + self.holes * features.holes as f64
+            + self.row_transitions * features.row_transitions as f64
+            + self.column_transitions * features.column_transitions as f64
+            + self.wells * features.wells as f64
+            + self.bumpiness * features.bumpiness as f64
+            + self.tspin_slots * features.tspin_slots as f64
+    }
+
+    /// the reward for clearing `lines` at once
+    pub fn clear(&self, lines: usize) -> f64 {
+        let mut reward = self.lines * lines as f64;
+        if lines == 4 { reward += self.tetris }
+        reward
+    }
+}
+
+impl std::fmt::Display for Weights {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, value) in WEIGHT_NAMES.iter().zip(self.values()) {
+            writeln!(f, "{} {}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Counts spots where a T pointing down fits under an overhang with both bottom corners filled.
+fn count_tspin_slots(grid: &[[Option<Cell>; 10]; 22]) -> usize {
+    let filled = |x: usize, y: usize| grid[y][x].is_some();
+
+    let mut slots = 0;
+    for y in 1..21 {
+        for x in 1..9 {
+            let open = !filled(x - 1, y) && !filled(x, y) && !filled(x + 1, y) && !filled(x, y + 1);
+            let corners = filled(x - 1, y + 1) && filled(x + 1, y + 1);
+            let overhang = filled(x - 1, y - 1) != filled(x + 1, y - 1) && !filled(x, y - 1);
+            if open && corners && overhang { slots += 1 }
+        }
+    }
+    slots
+}
@@ -0,0 +1,156 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::{gamestate::{Cell, GameState, Lock, Mode, Visibility, SPAWN_POSITION}, tetromino::{Shape, Tetromino}, util::{Action, Orientation, Pos2d, RotDirection}};
+
+/// How many inputs a locked piece took and how few it could have taken.
+///
+/// Holding a direction until the piece stops at a wall or the stack (DAS) counts as one input,
+/// so does a soft drop, and the final drop is free.
+#[derive(Clone, Copy)]
+pub struct Check {
+    pub shape: Shape,
+    pub used: usize,
+    pub optimal: usize,
+}
+
+impl Check {
+    pub fn is_fault(&self) -> bool {
+        self.used > self.optimal
+    }
+}
+
+/// Keeps a running count of finesse faults over the pieces locked in a game.
+#[derive(Default)]
+pub struct Finesse {
+    pub faults: usize,
+    /// the most recently locked piece
+    pub last: Option<Check>,
+    /// pieces already checked
+    pieces: usize,
+}
+
+impl Finesse {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks a newly locked piece, to be called once per frame after the game advanced.
+    pub fn update(&mut self, gamestate: &GameState) {
+        // undoing takes pieces back, those are not checked again
+        let new = gamestate.pieces > self.pieces;
+        self.pieces = gamestate.pieces;
+        if !new { return }
+
+        self.last = gamestate.last_lock.as_ref().and_then(check);
+        if self.last.is_some_and(|check| check.is_fault()) { self.faults += 1 }
+    }
+}
+
+/// Compares the inputs of `lock` to the fewest that reach the same cells from the spawn position.
+pub fn check(lock: &Lock) -> Option<Check> {
+    let mut board = GameState::new(Mode::Zen { gravity: 0 }, Visibility::Normal);
+    board.grid = lock.grid;
+
+    let spawn = Tetromino::new(lock.tetromino.shape, Orientation::North);
+    let target = get_cells(lock.tetromino, lock.pos);
+
+    Some(Check {
+        shape: lock.tetromino.shape,
+        used: count_inputs(&mut board, spawn, &lock.inputs),
+        optimal: get_optimal_inputs(&board, spawn, target)?,
+    })
+}
+
+/// Keys pressed for `inputs` of a `shape` on `grid` since it spawned, counted like `check` counts them
+/// except that the final drop is a key as well.
+pub fn count_keys(grid: &[[Option<Cell>; 10]; 22], shape: Shape, inputs: &[Action]) -> usize {
+    let mut board = GameState::new(Mode::Zen { gravity: 0 }, Visibility::Normal);
+    board.grid = *grid;
+
+    let dropped = matches!(inputs.last(), Some(Action::SoftDrop | Action::HardDrop));
+    count_inputs(&mut board, Tetromino::new(shape, Orientation::North), inputs) + dropped as usize
+}
+
+/// Replays `inputs` from the spawn position, counting runs of moves that end blocked as one input.
+fn count_inputs(board: &mut GameState, spawn: Tetromino, inputs: &[Action]) -> usize {
+    board.current = Some((spawn, SPAWN_POSITION));
+
+    let mut count = 0;
+    let mut i = 0;
+    while i < inputs.len() {
+        let action = inputs[i];
+        let run = inputs[i..].iter().take_while(|&&input| input == action).count();
+        for &input in inputs[i..i + run].iter() {
+            board.apply(input);
+        }
+        i += run;
+
+        count += match action {
+            Action::MoveLeft | Action::MoveRight => {
+                let (tetro, pos) = board.current.unwrap();
+                let step = if action == Action::MoveLeft { -1 } else { 1 };
+                let blocked = !board.fit_test(tetro, Pos2d { x: pos.x + step, y: pos.y });
+                if blocked && run > 1 { 1 } else { run }
+            },
+            Action::RotateClockwise | Action::RotateCounterClockwise => run,
+            // the drop that puts the piece down is free
+            Action::SoftDrop | Action::HardDrop => if i == inputs.len() { 0 } else { 1 },
+            Action::Hold => 0,
+        };
+    }
+
+    count
+}
+
+/// Searches the fewest inputs after which dropping the piece covers `target`, `None` if it can not.
+fn get_optimal_inputs(board: &GameState, spawn: Tetromino, target: [(isize, isize); 4]) -> Option<usize> {
+    if !board.fit_test(spawn, SPAWN_POSITION) { return None }
+
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::new();
+    visited.insert((spawn.orientation as u8, SPAWN_POSITION.x, SPAWN_POSITION.y));
+    queue.push_back((spawn, SPAWN_POSITION, 0));
+
+    while let Some((tetro, pos, count)) = queue.pop_front() {
+        if get_cells(tetro, drop(board, tetro, pos, 0, 1)) == target { return Some(count) }
+
+        let moves = [
+            Some((tetro, drop(board, tetro, pos, -1, 0))),
+            Some((tetro, drop(board, tetro, pos, 1, 0))),
+            Some((tetro, step(board, tetro, pos, -1))),
+            Some((tetro, step(board, tetro, pos, 1))),
+            Some((tetro, drop(board, tetro, pos, 0, 1))),
+            board.rotated(tetro, pos, RotDirection::Clockwise),
+            board.rotated(tetro, pos, RotDirection::CounterClockwise),
+        ];
+
+        for (tetro, pos) in moves.into_iter().flatten() {
+            if visited.insert((tetro.orientation as u8, pos.x, pos.y)) {
+                queue.push_back((tetro, pos, count + 1));
+            }
+        }
+    }
+
+    None
+}
+
+/// one cell sideways, or staying put if blocked
+fn step(board: &GameState, tetro: Tetromino, pos: Pos2d, x: isize) -> Pos2d {
+    let moved = Pos2d { x: pos.x + x, y: pos.y };
+    if board.fit_test(tetro, moved) { moved } else { pos }
+}
+
+/// as far as the piece goes in one direction
+fn drop(board: &GameState, tetro: Tetromino, mut pos: Pos2d, x: isize, y: isize) -> Pos2d {
+    while board.fit_test(tetro, Pos2d { x: pos.x + x, y: pos.y + y }) {
+        pos = Pos2d { x: pos.x + x, y: pos.y + y };
+    }
+    pos
+}
+
+/// grid cells covered by the piece as (y, x), sorted
+fn get_cells(tetro: Tetromino, pos: Pos2d) -> [(isize, isize); 4] {
+    let mut cells = tetro.get_tiles().map(|tile| (tile.y as isize + pos.y, tile.x as isize + pos.x));
+    cells.sort();
+    cells
+}
@@ -0,0 +1,668 @@
+//! Encoding and decoding of fumen (v115) diagrams, the format used to share positions.
+//!
+//! A fumen field has 23 rows plus a garbage row below them, rows are counted from the bottom.
+//! Our grid maps onto the lower 22 rows, the topmost fumen row and the garbage row are dropped.
+//! The hold and queue of a position travel in a quiz comment of the form `#Q=[hold](current)queue`.
+
+use crate::{gamestate::{Cell, GameState, Phase}, tetromino::{Color, Shape, Tetromino}, util::{Orientation, Pos2d}};
+
+const ENCODE_TABLE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const COMMENT_TABLE: &[u8] = b" !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~";
+
+/// escaped comment characters a page can hold
+const COMMENT_LIMIT: usize = 4095;
+
+const FIELD_TOP: usize = 23;
+/// the 23 rows and the garbage row
+const FIELD_BLOCKS: usize = (FIELD_TOP + 1) * 10;
+
+/// fumen cell values indexed by row, row 0 is the garbage row and row 1 the bottom of the field
+type Field = [[u8; 10]; FIELD_TOP + 1];
+
+/// One page of a fumen: the grid before the piece is placed, the piece and the page comment.
+#[derive(Clone)]
+pub struct Page {
+    pub grid: [[Option<Color>; 10]; 22],
+    pub piece: Option<(Tetromino, Pos2d)>,
+    pub comment: String,
+    /// whether the piece is placed into the grid of the following pages
+    pub lock: bool,
+}
+
+impl Page {
+    pub fn from_gamestate(gamestate: &GameState) -> Self {
+        let shape_char = |shape: Option<Shape>| shape.map(|shape| shape.to_char().to_string()).unwrap_or_default();
+
+        let comment = format!(
+            "#Q=[{}]({}){}",
+            shape_char(gamestate.hold),
+            shape_char(gamestate.current.map(|(tetro, _)| tetro.shape)),
+            gamestate.next.iter().rev().map(|shape| shape.to_char()).collect::<String>(),
+        );
+
+        Self {
+            grid: gamestate.grid.map(|line| line.map(|cell| cell.map(|cell| cell.color))),
+            piece: gamestate.current,
+            comment,
+            lock: false,
+        }
+    }
+
+    /// Sets up `gamestate` with this page's grid and piece, and hold and queue if the comment is a quiz.
+    pub fn apply(&self, gamestate: &mut GameState) {
+        gamestate.grid = self.grid.map(|line| line.map(|cell| cell.map(|color| Cell { color, locked_at: gamestate.ticks })));
+
+        if let Some((hold, current, queue)) = parse_quiz(&self.comment) {
+            gamestate.hold = hold;
+            gamestate.next = queue.into_iter().rev().collect();
+
+            // without a placed piece the quiz's current piece spawns next
+            if let (None, Some(current)) = (self.piece, current) {
+                gamestate.next.push(current);
+            }
+        }
+
+        if let Some(piece) = self.piece {
+            gamestate.current = Some(piece);
+            gamestate.phase = Phase::Active;
+        }
+    }
+}
+
+pub fn decode(fumen: &str) -> Result<Vec<Page>, String> {
+    let data = fumen.trim().strip_prefix("v115@").ok_or("only v115 fumens are supported")?;
+    let mut values = Values::parse(data)?;
+
+    let mut pages = Vec::new();
+    let mut field: Field = [[0; 10]; FIELD_TOP + 1];
+    let mut repeat = 0;
+    let mut comment = String::new();
+
+    while !values.is_empty() {
+        if repeat > 0 {
+            repeat -= 1;
+        } else if !decode_field(&mut values, &mut field)? {
+            repeat = values.poll(1)?;
+        }
+
+        let mut action = values.poll(3)?;
+        let piece = action % 8;
+        action /= 8;
+        let rotation = action % 4;
+        action /= 4;
+        let coordinate = action % FIELD_BLOCKS;
+        action /= FIELD_BLOCKS;
+        let rise = action % 2 == 1;
+        let mirror = (action / 2) % 2 == 1;
+        let has_comment = (action / 8) % 2 == 1;
+        let lock = (action / 16) % 2 == 0;
+
+        if has_comment {
+            let length = values.poll(2)?;
+            let mut escaped = String::new();
+
+            for _ in 0..length.div_ceil(4) {
+                let mut value = values.poll(5)?;
+                if value >= (COMMENT_TABLE.len() + 1).pow(4) { return Err("invalid comment".to_string()) }
+
+                for _ in 0..4 {
+                    let c = COMMENT_TABLE.get(value % (COMMENT_TABLE.len() + 1)).ok_or("invalid comment character")?;
+                    escaped.push(*c as char);
+                    value /= COMMENT_TABLE.len() + 1;
+                }
+            }
+
+            comment = unescape(&escaped.chars().take(length).collect::<String>());
+        }
+
+        let operation = decode_operation(piece, rotation, coordinate);
+
+        pages.push(Page {
+            grid: to_grid(&field),
+            piece: operation.map(|(shape, orientation, x, y)| to_piece(shape, orientation, x, y)),
+            comment: comment.clone(),
+            lock,
+        });
+
+        if lock {
+            if let Some((shape, orientation, x, y)) = operation {
+                for (dx, dy) in get_blocks(shape, orientation) {
+                    let (x, y) = (x + dx, y + dy);
+                    if (0..10).contains(&x) && (0..FIELD_TOP as isize).contains(&y) {
+                        field[(y + 1) as usize][x as usize] = get_piece_value(shape);
+                    }
+                }
+
+                // the quiz of a comment advances with every placed piece
+                if let Some(quiz) = advance_quiz(&comment, shape) { comment = quiz }
+            }
+
+            clear_lines(&mut field);
+            if rise { rise_garbage(&mut field) }
+            if mirror { field[1..].iter_mut().for_each(|row| row.reverse()) }
+        }
+    }
+
+    Ok(pages)
+}
+
+pub fn encode(pages: &[Page]) -> String {
+    let mut values = Values { digits: Vec::new(), index: 0 };
+    let mut prev: Field = [[0; 10]; FIELD_TOP + 1];
+    let mut last_repeat: Option<usize> = None;
+    let mut prev_comment = String::new();
+
+    for (index, page) in pages.iter().enumerate() {
+        let field = to_field(&page.grid, &prev);
+
+        // unchanged fields are only stored once, followed by how often they repeat
+        let mut field_values = Values { digits: Vec::new(), index: 0 };
+        if encode_field(&mut field_values, &prev, &field) {
+            values.digits.append(&mut field_values.digits);
+            last_repeat = None;
+        } else {
+            match last_repeat {
+                Some(i) if values.digits[i] < 63 => values.digits[i] += 1,
+                _ => {
+                    values.digits.append(&mut field_values.digits);
+                    values.push(0, 1);
+                    last_repeat = Some(values.digits.len() - 1);
+                },
+            }
+        }
+
+        let has_comment = page.comment != prev_comment;
+        prev_comment = page.comment.clone();
+
+        let (piece, rotation, coordinate) = match page.piece {
+            Some((tetro, pos)) => {
+                let (x, y) = from_piece(tetro, pos);
+                let rotation = match tetro.orientation {
+                    Orientation::South => 0,
+                    Orientation::East => 1,
+                    Orientation::North => 2,
+                    Orientation::West => 3,
+                };
+                (get_piece_value(tetro.shape) as usize, rotation, encode_coordinate(tetro.shape, tetro.orientation, x, y))
+            },
+            None => (0, 0, (FIELD_TOP - 22 - 1) * 10),
+        };
+
+        let mut action = if page.lock { 0 } else { 1 };
+        action = action * 2 + has_comment as usize;
+        action = action * 2 + (index == 0) as usize;
+        action *= 4;
+        action = action * FIELD_BLOCKS + coordinate;
+        action = action * 4 + rotation;
+        action = action * 8 + piece;
+        values.push(action, 3);
+
+        if has_comment {
+            // cut before the character that does not fit anymore, an escape sequence must stay whole
+            let mut comment = String::new();
+            for c in page.comment.chars() {
+                let escaped = escape(&c.to_string());
+                if comment.len() + escaped.len() > COMMENT_LIMIT { break }
+                comment.push_str(&escaped);
+            }
+            values.push(comment.len(), 2);
+
+            for chunk in comment.as_bytes().chunks(4) {
+                let value = chunk.iter().rev().fold(0, |value, &c| {
+                    let index = COMMENT_TABLE.iter().position(|&t| t == c).unwrap_or(0);
+                    value * (COMMENT_TABLE.len() + 1) + index
+                });
+                values.push(value, 5);
+            }
+        }
+
+        let mut field = field;
+        if page.lock {
+            if let Some((tetro, pos)) = page.piece {
+                for tile in tetro.get_tiles() {
+                    let (x, y) = (tile.x as isize + pos.x, 21 - (tile.y as isize + pos.y));
+                    if (0..10).contains(&x) && (0..FIELD_TOP as isize).contains(&y) {
+                        field[(y + 1) as usize][x as usize] = get_piece_value(tetro.shape);
+                    }
+                }
+            }
+            clear_lines(&mut field);
+        }
+        prev = field;
+    }
+
+    let data = values.digits.iter().map(|&digit| ENCODE_TABLE[digit] as char).collect::<String>();
+
+    // fumens are broken up with a '?' every 47 characters, counting the "v115@" prefix
+    let mut out = "v115@".to_string();
+    for (i, c) in data.chars().enumerate() {
+        if i >= 42 && (i - 42) % 47 == 0 { out.push('?') }
+        out.push(c);
+    }
+    out
+}
+
+/// Splits a quiz comment into hold, current piece and queue.
+fn parse_quiz(comment: &str) -> Option<(Option<Shape>, Option<Shape>, Vec<Shape>)> {
+    let quiz = comment.strip_prefix("#Q=[")?;
+    let (hold, rest) = quiz.split_once("](")?;
+    let (current, queue) = rest.split_once(')')?;
+
+    Some((
+        hold.chars().next().and_then(Shape::from_char),
+        current.chars().next().and_then(Shape::from_char),
+        queue.chars().filter_map(Shape::from_char).collect(),
+    ))
+}
+
+/// The quiz after `used` was placed, either as the current piece, from hold or by holding the current one.
+fn advance_quiz(comment: &str, used: Shape) -> Option<String> {
+    let (mut hold, mut current, mut queue) = parse_quiz(comment)?;
+    let mut queue = queue.drain(..);
+
+    if current.is_none() { current = queue.next() }
+
+    if current == Some(used) {
+        current = queue.next();
+    } else if hold == Some(used) {
+        hold = current;
+        current = queue.next();
+    } else if hold.is_none() && queue.as_slice().first() == Some(&used) {
+        hold = current;
+        queue.next();
+        current = queue.next();
+    } else {
+        return None;
+    }
+
+    let shape_char = |shape: Option<Shape>| shape.map(|shape| shape.to_char().to_string()).unwrap_or_default();
+    Some(format!("#Q=[{}]({}){}", shape_char(hold), shape_char(current), queue.map(|shape| shape.to_char()).collect::<String>()))
+}
+
+/// base 64 digits, values are stored little endian over a fixed number of digits
+struct Values {
+    digits: Vec<usize>,
+    index: usize,
+}
+
+impl Values {
+    fn parse(data: &str) -> Result<Self, String> {
+        let digits = data.bytes()
+            .filter(|&c| c != b'?')
+            .map(|c| ENCODE_TABLE.iter().position(|&t| t == c).ok_or(format!("invalid character '{}'", c as char)))
+            .collect::<Result<Vec<usize>, String>>()?;
+
+        Ok(Self { digits, index: 0 })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.index >= self.digits.len()
+    }
+
+    fn poll(&mut self, count: usize) -> Result<usize, String> {
+        if self.index + count > self.digits.len() { return Err("unexpected end of fumen".to_string()) }
+
+        let value = self.digits[self.index..self.index + count].iter().rev().fold(0, |value, &digit| value * 64 + digit);
+        self.index += count;
+        Ok(value)
+    }
+
+    fn push(&mut self, mut value: usize, count: usize) {
+        for _ in 0..count {
+            self.digits.push(value % 64);
+            value /= 64;
+        }
+    }
+}
+
+/// Applies the run length encoded differences to `field`, returns whether anything changed.
+fn decode_field(values: &mut Values, field: &mut Field) -> Result<bool, String> {
+    let mut index = 0;
+    let mut changed = true;
+
+    while index < FIELD_BLOCKS {
+        let value = values.poll(2)?;
+        let diff = value / FIELD_BLOCKS;
+        let count = value % FIELD_BLOCKS + 1;
+
+        if diff == 8 && count == FIELD_BLOCKS { changed = false }
+        if index + count > FIELD_BLOCKS { return Err("field data overflows the field".to_string()) }
+
+        for i in index..index + count {
+            let cell = &mut field[FIELD_TOP - i / 10][i % 10];
+            *cell = (*cell as isize + diff as isize - 8).clamp(0, 8) as u8;
+        }
+        index += count;
+    }
+
+    Ok(changed)
+}
+
+/// Pushes the run length encoded differences between `prev` and `field`, returns whether anything changed.
+fn encode_field(values: &mut Values, prev: &Field, field: &Field) -> bool {
+    let diffs = (0..FIELD_BLOCKS).map(|i| {
+        let row = FIELD_TOP - i / 10;
+        (field[row][i % 10] as isize - prev[row][i % 10] as isize + 8) as usize
+    }).collect::<Vec<usize>>();
+
+    let mut start = 0;
+    for i in 1..=FIELD_BLOCKS {
+        if i == FIELD_BLOCKS || diffs[i] != diffs[start] {
+            values.push(diffs[start] * FIELD_BLOCKS + (i - start - 1), 2);
+            start = i;
+        }
+    }
+
+    diffs.iter().any(|&diff| diff != 8)
+}
+
+fn clear_lines(field: &mut Field) {
+    let rows = field[1..].iter().filter(|row| row.contains(&0)).copied().collect::<Vec<[u8; 10]>>();
+
+    for (i, row) in field[1..].iter_mut().enumerate() {
+        *row = rows.get(i).copied().unwrap_or([0; 10]);
+    }
+}
+
+fn rise_garbage(field: &mut Field) {
+    for row in (1..=FIELD_TOP).rev() {
+        field[row] = field[row - 1];
+    }
+    field[0] = [0; 10];
+}
+
+fn to_grid(field: &Field) -> [[Option<Color>; 10]; 22] {
+    let mut grid = [[None; 10]; 22];
+
+    for (y, line) in grid.iter_mut().enumerate() {
+        for (x, cell) in line.iter_mut().enumerate() {
+            *cell = match field[22 - y][x] {
+                0 => None,
+                8 => Some(Color::Gray),
+                value => Some(get_value_piece(value).color()),
+            };
+        }
+    }
+
+    grid
+}
+
+/// our grid as fumen field, keeping the rows of `prev` we can't represent
+fn to_field(grid: &[[Option<Color>; 10]; 22], prev: &Field) -> Field {
+    let mut field = *prev;
+
+    for (y, line) in grid.iter().enumerate() {
+        for (x, cell) in line.iter().enumerate() {
+            field[22 - y][x] = match cell {
+                None => 0,
+                Some(Color::Gray) => 8,
+                Some(color) => get_piece_value(Shape::from_color(*color).unwrap()),
+            };
+        }
+    }
+
+    field
+}
+
+fn get_piece_value(shape: Shape) -> u8 {
+    match shape {
+        Shape::I => 1,
+        Shape::L => 2,
+        Shape::O => 3,
+        Shape::Z => 4,
+        Shape::T => 5,
+        Shape::J => 6,
+        Shape::S => 7,
+    }
+}
+
+fn get_value_piece(value: u8) -> Shape {
+    match value {
+        1 => Shape::I,
+        2 => Shape::L,
+        3 => Shape::O,
+        4 => Shape::Z,
+        5 => Shape::T,
+        6 => Shape::J,
+        _ => Shape::S,
+    }
+}
+
+/// block offsets around the rotation center, y pointing up
+fn get_blocks(shape: Shape, orientation: Orientation) -> [(isize, isize); 4] {
+    let blocks = match shape {
+        Shape::I => [(0, 0), (-1, 0), (1, 0), (2, 0)],
+        Shape::T => [(0, 0), (-1, 0), (1, 0), (0, 1)],
+        Shape::O => [(0, 0), (1, 0), (0, 1), (1, 1)],
+        Shape::L => [(0, 0), (-1, 0), (1, 0), (1, 1)],
+        Shape::J => [(0, 0), (-1, 0), (1, 0), (-1, 1)],
+        Shape::S => [(0, 0), (-1, 0), (0, 1), (1, 1)],
+        Shape::Z => [(0, 0), (1, 0), (0, 1), (-1, 1)],
+    };
+
+    blocks.map(|(x, y)| match orientation {
+        Orientation::North => (x, y),
+        Orientation::East => (y, -x),
+        Orientation::South => (-x, -y),
+        Orientation::West => (-y, x),
+    })
+}
+
+/// the action's piece as shape, orientation and rotation center
+fn decode_operation(piece: usize, rotation: usize, coordinate: usize) -> Option<(Shape, Orientation, isize, isize)> {
+    if piece == 0 || piece == 8 { return None }
+
+    let shape = get_value_piece(piece as u8);
+    let orientation = match rotation {
+        0 => Orientation::South,
+        1 => Orientation::East,
+        2 => Orientation::North,
+        _ => Orientation::West,
+    };
+
+    let mut x = (coordinate % 10) as isize;
+    let mut y = FIELD_TOP as isize - (coordinate / 10) as isize - 1;
+
+    // fumen places these pieces by a different cell than their rotation center
+    match (shape, orientation) {
+        (Shape::O, Orientation::West) => { x += 1; y -= 1 },
+        (Shape::O, Orientation::South) => x += 1,
+        (Shape::O, Orientation::North) => y -= 1,
+        (Shape::I, Orientation::South) => x += 1,
+        (Shape::I, Orientation::West) => y -= 1,
+        (Shape::S, Orientation::North) => y -= 1,
+        (Shape::S, Orientation::East) => x -= 1,
+        (Shape::Z, Orientation::North) => y -= 1,
+        (Shape::Z, Orientation::West) => x += 1,
+        _ => (),
+    }
+
+    Some((shape, orientation, x, y))
+}
+
+fn encode_coordinate(shape: Shape, orientation: Orientation, mut x: isize, mut y: isize) -> usize {
+    match (shape, orientation) {
+        (Shape::O, Orientation::West) => { x -= 1; y += 1 },
+        (Shape::O, Orientation::South) => x -= 1,
+        (Shape::O, Orientation::North) => y += 1,
+        (Shape::I, Orientation::South) => x -= 1,
+        (Shape::I, Orientation::West) => y += 1,
+        (Shape::S, Orientation::North) => y += 1,
+        (Shape::S, Orientation::East) => x += 1,
+        (Shape::Z, Orientation::North) => y += 1,
+        (Shape::Z, Orientation::West) => x -= 1,
+        _ => (),
+    }
+
+    ((FIELD_TOP as isize - y - 1) * 10 + x) as usize
+}
+
+/// converts a fumen piece into our tetromino and its position in the grid
+fn to_piece(shape: Shape, orientation: Orientation, x: isize, y: isize) -> (Tetromino, Pos2d) {
+    let tetro = Tetromino::new(shape, orientation);
+    let blocks = get_blocks(shape, orientation);
+
+    let min_x = blocks.iter().map(|&(dx, _)| x + dx).min().unwrap();
+    let min_y = blocks.iter().map(|&(_, dy)| 21 - (y + dy)).min().unwrap();
+    let tile_x = tetro.get_tiles().iter().map(|tile| tile.x as isize).min().unwrap();
+    let tile_y = tetro.get_tiles().iter().map(|tile| tile.y as isize).min().unwrap();
+
+    (tetro, Pos2d { x: min_x - tile_x, y: min_y - tile_y })
+}
+
+/// the rotation center of our tetromino in fumen coordinates
+fn from_piece(tetro: Tetromino, pos: Pos2d) -> (isize, isize) {
+    let blocks = get_blocks(tetro.shape, tetro.orientation);
+
+    let min_x = tetro.get_tiles().iter().map(|tile| tile.x as isize + pos.x).min().unwrap();
+    let max_y = tetro.get_tiles().iter().map(|tile| 21 - (tile.y as isize + pos.y)).max().unwrap();
+    let block_x = blocks.iter().map(|&(dx, _)| dx).min().unwrap();
+    let block_y = blocks.iter().map(|&(_, dy)| dy).max().unwrap();
+
+    (min_x - block_x, max_y - block_y)
+}
+
+/// the comment escaping of javascript's `escape`, which fumen comments are stored with
+fn escape(text: &str) -> String {
+    let mut out = String::new();
+
+    for unit in text.encode_utf16() {
+        match char::from_u32(unit as u32) {
+            Some(c) if c.is_ascii_alphanumeric() || "@*_+-./".contains(c) => out.push(c),
+            _ if unit < 256 => out.push_str(&format!("%{:02X}", unit)),
+            _ => out.push_str(&format!("%u{:04X}", unit)),
+        }
+    }
+
+    out
+}
+
+fn unescape(text: &str) -> String {
+    let mut units = Vec::new();
+    let bytes = text.as_bytes();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = |from: usize, len: usize| text.get(from..from + len).and_then(|hex| u16::from_str_radix(hex, 16).ok());
+
+        match (bytes[i], bytes.get(i + 1)) {
+            (b'%', Some(b'u')) if hex(i + 2, 4).is_some() => { units.push(hex(i + 2, 4).unwrap()); i += 6 },
+            (b'%', _) if hex(i + 1, 2).is_some() => { units.push(hex(i + 1, 2).unwrap()); i += 3 },
+            (c, _) => { units.push(c as u16); i += 1 },
+        }
+    }
+
+    String::from_utf16_lossy(&units)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gamestate::{Mode, Visibility};
+
+    /// the grid as one character per cell, so grids can be compared
+    fn cells(grid: &[[Option<Color>; 10]; 22]) -> String {
+        grid.iter().flatten().map(|cell| match cell {
+            None => '.',
+            Some(color) => Shape::from_color(*color).map_or('G', |shape| shape.to_char()),
+        }).collect()
+    }
+
+    fn page() -> Page {
+        let mut gamestate = GameState::new(Mode::Zen { gravity: 0 }, Visibility::Normal);
+        gamestate.grid[21] = [Some(Cell { color: Color::Gray, locked_at: 0 }); 10];
+        gamestate.grid[21][4] = None;
+        gamestate.grid[20][0] = Some(Cell { color: Shape::T.color(), locked_at: 0 });
+        gamestate.current = Some((Tetromino::new(Shape::L, Orientation::East), Pos2d { x: 5, y: 17 }));
+        gamestate.hold = Some(Shape::I);
+        gamestate.next = vec![Shape::O, Shape::S];
+        Page::from_gamestate(&gamestate)
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut pages = vec![page(), page()];
+        pages[1].comment = "snow ☃ and 100% escapes".to_string();
+        pages[1].piece = None;
+
+        let decoded = decode(&encode(&pages)).unwrap();
+        assert_eq!(decoded.len(), pages.len());
+
+        for (page, decoded) in pages.iter().zip(&decoded) {
+            assert_eq!(cells(&decoded.grid), cells(&page.grid));
+            assert_eq!(decoded.comment, page.comment);
+            assert_eq!(decoded.lock, page.lock);
+            assert_eq!(
+                decoded.piece.map(|(tetro, pos)| (tetro.shape.to_char(), tetro.orientation.to_string(), pos.x, pos.y)),
+                page.piece.map(|(tetro, pos)| (tetro.shape.to_char(), tetro.orientation.to_string(), pos.x, pos.y)),
+            );
+        }
+    }
+
+    /// grid cells a decoded piece covers, as (row, column)
+    fn piece_cells(page: &Page) -> Vec<(isize, isize)> {
+        let (tetro, pos) = page.piece.unwrap();
+        let mut cells: Vec<(isize, isize)> = tetro.get_tiles().iter().map(|tile| (tile.y as isize + pos.y, tile.x as isize + pos.x)).collect();
+        cells.sort();
+        cells
+    }
+
+    // the fumens below are worked out by hand from the v115 format, not with `encode`,
+    // so they pin the field order, piece values, rotations and coordinates of the format itself
+
+    #[test]
+    fn decodes_empty_page() {
+        let pages = decode("v115@vhAAgH").unwrap();
+        assert_eq!(pages.len(), 1);
+        assert_eq!(cells(&pages[0].grid), ".".repeat(220));
+        assert!(pages[0].piece.is_none());
+        assert!(pages[0].lock);
+    }
+
+    #[test]
+    fn decodes_field() {
+        // 221 empty cells, 9 gray ones and the empty garbage row: the bottom row with a hole in the first column
+        let pages = decode("v115@chI8JeAgH").unwrap();
+        assert_eq!(pages.len(), 1);
+        assert_eq!(cells(&pages[0].grid), format!("{}.GGGGGGGGG", ".".repeat(210)));
+        assert!(pages[0].piece.is_none());
+    }
+
+    #[test]
+    fn decodes_pieces() {
+        // an unchanged field repeated for the second page, then a T in spawn rotation centered on the
+        // bottom row in the fifth column, and an I rotated right centered on the third row in the last column
+        let pages = decode("v115@vhBVQJpoB").unwrap();
+        assert_eq!(pages.len(), 2);
+
+        let (tetro, _) = pages[0].piece.unwrap();
+        assert!(tetro.shape == Shape::T && tetro.orientation == Orientation::North);
+        assert_eq!(piece_cells(&pages[0]), vec![(20, 4), (21, 3), (21, 4), (21, 5)]);
+
+        let (tetro, _) = pages[1].piece.unwrap();
+        assert!(tetro.shape == Shape::I && tetro.orientation == Orientation::East);
+        assert_eq!(piece_cells(&pages[1]), vec![(18, 9), (19, 9), (20, 9), (21, 9)]);
+
+        // the T was locked into the second page's field
+        assert_eq!(cells(&pages[1].grid), format!("{}....T........TTT....", ".".repeat(200)));
+    }
+
+    #[test]
+    fn long_comment_is_cut_between_characters() {
+        let mut page = page();
+        page.comment = "☃".repeat(1000);
+
+        let decoded = decode(&encode(&[page])).unwrap();
+        assert!(!decoded[0].comment.is_empty());
+        assert!(decoded[0].comment.chars().all(|c| c == '☃'));
+        assert!(escape(&decoded[0].comment).len() <= COMMENT_LIMIT);
+    }
+
+    #[test]
+    fn malformed_input() {
+        assert!(decode("v115@vhAAg0EAB/4nD").is_err());
+        assert!(decode("v115@vhAAg").is_err());
+        assert!(decode("v115@vh!Agh").is_err());
+        assert!(decode("v114@vhAAgH").is_err());
+    }
+}
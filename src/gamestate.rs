@@ -1,67 +1,460 @@
 use std::fmt::Display;
-use rand::thread_rng;
-use rand::seq::SliceRandom;
 
-use crate::{tetromino::{self, Shape, Color, Tetromino}, util::{self, Pos2d}};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+use crate::{finesse, randomizer::{Randomizer, Sequence}, stats::Stats, tetromino::{self, Color, Tetromino}, timing::{self, Timing}, util::{self, Pos2d}};
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum State {
     Running,
     Lost,
+    Won,
+}
+
+/// Lifecycle of a piece, the values are the frames left in the phase.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Phase {
+    /// no piece on the field, the next one spawns once the entry delay (ARE) ran out
+    Entry(usize),
+    Active,
+    /// full lines are still shown and get removed once the delay ran out
+    LineClear(usize),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Mode {
+    Marathon,
+    /// garbage rows are pushed in from the bottom at a shrinking interval
+    Survival,
+    /// TGM style, gravity and delays are taken from tables indexed by the section level
+    Master,
+    /// practice without topping out, a full stack clears the board; gravity is in 1/256 rows per frame
+    Zen { gravity: usize },
+}
+
+impl Display for Mode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mode::Marathon => write!(f, "marathon"),
+            Mode::Survival => write!(f, "survival"),
+            Mode::Master => write!(f, "master"),
+            Mode::Zen { gravity: 0 } => write!(f, "zen"),
+            Mode::Zen { gravity } => write!(f, "zen {}", gravity),
+        }
+    }
+}
+
+/// How locked tiles are shown while the game is running, the whole stack is revealed once it ends.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Visibility {
+    #[default]
+    Normal,
+    Invisible,
+    /// tiles fade out over `FADE_FRAMES` after locking
+    Fading,
 }
 
+impl Display for Visibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Visibility::Normal => write!(f, "normal"),
+            Visibility::Invisible => write!(f, "invisible"),
+            Visibility::Fading => write!(f, "fading"),
+        }
+    }
+}
+
+pub const FADE_FRAMES: usize = 150;
+
+/// where new pieces enter the field, facing north
+pub const SPAWN_POSITION: Pos2d = Pos2d { x: 3, y: 0 };
+
+/// bumped whenever a change to `GameState` breaks old save files
+const SAVE_VERSION: u64 = 1;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Cell {
+    pub color: tetromino::Color,
+    /// frame in which the tile was locked into the grid
+    pub locked_at: usize,
+}
+
+/// A piece as it was locked and what led there.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Lock {
+    pub tetromino: Tetromino,
+    pub pos: Pos2d,
+    /// inputs applied to the piece since it entered, holding starts over
+    pub inputs: Vec<util::Action>,
+    /// the grid just before the piece locked
+    pub grid: [[Option<Cell>; 10]; 22],
+    /// full lines the piece completed
+    #[serde(default)]
+    pub lines: usize,
+    #[serde(default)]
+    pub tspin: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct GameState {
-    pub grid: [[Option<tetromino::Color>; 10]; 22],
+    pub grid: [[Option<Cell>; 10]; 22],
     pub current: Option<(tetromino::Tetromino, util::Pos2d)>,
     pub state: State,
     pub next: Vec<tetromino::Shape>,
+    pub hold: Option<tetromino::Shape>,
+    /// hold can only be used once per piece
+    pub hold_used: bool,
+    pub randomizer: Randomizer,
+    /// deals the pieces instead of the randomizer, the game is won once a sequence that does not repeat ran out
+    #[serde(default)]
+    pub sequence: Option<Sequence>,
     pub score: usize,
+    /// number of pieces locked so far
+    pub pieces: usize,
+    /// inputs applied to the current piece since it entered
+    #[serde(default)]
+    pub inputs: Vec<util::Action>,
+    #[serde(default)]
+    pub last_lock: Option<Lock>,
+    #[serde(default)]
+    pub stats: Stats,
+    pub mode: Mode,
+    pub visibility: Visibility,
+    pub phase: Phase,
+    /// section level in master mode
+    pub level: usize,
+    /// number of frames since the game started
+    pub ticks: usize,
+    next_garbage: usize,
+    /// fraction of a row fallen, in 1/256ths
+    gravity_progress: usize,
+    lock_frames: usize,
 }
 
 impl GameState {
-    pub fn new() -> Self {
-        Self {
+    pub fn new(mode: Mode, visibility: Visibility) -> Self {
+        let mut gamestate = Self {
             grid: [[None; 10]; 22],
             current: None,
             state: State::Running,
             next: Vec::new(),
+            hold: None,
+            hold_used: false,
+            randomizer: Randomizer::from_entropy(),
+            sequence: None,
             score: 0,
+            pieces: 0,
+            inputs: Vec::new(),
+            last_lock: None,
+            stats: Stats::new(),
+            mode,
+            visibility,
+            phase: Phase::Entry(0),
+            level: 0,
+            ticks: 0,
+            next_garbage: 0,
+            gravity_progress: 0,
+            lock_frames: 0,
+        };
+        gamestate.next_garbage = gamestate.garbage_interval();
+        gamestate
+    }
+
+    /// A game dealing the pieces of `sequence` in order rather than shuffled bags.
+    pub fn with_sequence(mode: Mode, visibility: Visibility, sequence: Sequence) -> Self {
+        Self { sequence: Some(sequence), ..Self::new(mode, visibility) }
+    }
+
+    /// Restores a game suspended with `save`.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut save: serde_json::Value = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+
+        match save["version"].as_u64() {
+            Some(SAVE_VERSION) => {},
+            Some(version) => return Err(format!("{}: unsupported save version {}", path, version)),
+            None => return Err(format!("{}: not a save file", path)),
+        }
+
+        serde_json::from_value(save["gamestate"].take()).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Writes the whole game, including randomizer and timers, as versioned json.
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let save = serde_json::json!({ "version": SAVE_VERSION, "gamestate": self });
+        std::fs::write(path, save.to_string()).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Deals the next bag once the preview ran out, which stays empty when a sequence that does not repeat is over.
+    fn refill(&mut self) {
+        if self.next.is_empty() {
+            self.next = match self.sequence.as_mut() {
+                Some(sequence) => sequence.next_bag(),
+                None => self.randomizer.next_bag(),
+            };
         }
     }
 
     pub fn spawn(&mut self) {
-        if self.next.len() == 0 {
-            self.next = vec![Shape::O, Shape::I, Shape::L, Shape::J, Shape::S, Shape::Z, Shape::T];
-            self.next.shuffle(&mut thread_rng());
+        self.refill();
+
+        let shape = match self.next.pop() {
+            Some(shape) => shape,
+            None => { self.state = State::Won; return },
+        };
+        self.current = Some((tetromino::Tetromino::new(shape, util::Orientation::North), SPAWN_POSITION));
+        self.inputs.clear();
+    }
+
+    /// Swaps the current piece with the held one, taking the next piece if nothing is held yet.
+    /// Does nothing when there is no next piece to take, so only a lock can end a sequence.
+    pub fn hold(&mut self) {
+        if self.hold_used { return }
+
+        if self.hold.is_none() {
+            self.refill();
+            if self.next.is_empty() { return }
         }
 
-        let shape = self.next.pop().unwrap();
-        self.current = Some((tetromino::Tetromino::new(shape, util::Orientation::North), Pos2d{ x: 3, y: 0 }))
+        if let Some((tetro, _)) = self.current {
+            self.stats.keys += finesse::count_keys(&self.grid, tetro.shape, &self.inputs);
+
+            match self.hold.replace(tetro.shape) {
+                Some(shape) => self.current = Some((tetromino::Tetromino::new(shape, util::Orientation::North), SPAWN_POSITION)),
+                None => self.spawn(),
+            }
+
+            self.inputs.clear();
+            self.hold_used = true;
+            self.lock_frames = 0;
+            self.gravity_progress = 0;
+        }
     }
 
-    pub fn step(&mut self) {
-        match self.fall() {
-            Ok(_) => (),
-            Err(_) => {
-                // put current in grid
-                if let Some((tetro, pos)) = self.current {
-                    for tile in tetro.get_tiles() {
-                        self.grid[(tile.y as isize + pos.y) as usize][(tile.x as isize + pos.x) as usize] = Some(tetro.color);
-                    }
+    pub fn timing(&self) -> Timing {
+        match self.mode {
+            Mode::Master => timing::master(self.level),
+            Mode::Zen { gravity } => Timing { gravity, ..timing::CLASSIC },
+            _ => timing::CLASSIC,
+        }
+    }
+
+    /// Advances the game by one frame.
+    pub fn tick(&mut self) {
+        if self.state != State::Running { return }
+
+        self.ticks += 1;
+        self.stats.frames += 1;
+        let timing = self.timing();
+
+        match self.phase {
+            Phase::Entry(0) => {
+                self.spawn();
+                self.phase = Phase::Active;
+
+                if self.mode == Mode::Master && self.level % 100 != 99 && self.level != timing::MASTER_MAX_LEVEL - 1 {
+                    self.level += 1;
                 }
 
-                self.current = None;
+                self.apply_gravity(timing);
+            },
+            Phase::Entry(frames) => self.phase = Phase::Entry(frames - 1),
+            Phase::LineClear(0) => {
+                self.remove_lines();
+                self.phase = Phase::Entry(timing.are);
+            },
+            Phase::LineClear(frames) => self.phase = Phase::LineClear(frames - 1),
+            Phase::Active => self.apply_gravity(timing),
+        }
+
+        if self.mode == Mode::Survival && self.ticks >= self.next_garbage {
+            let hole = self.randomizer.garbage_hole();
+            self.insert_garbage(1, hole);
+            self.next_garbage = self.ticks + self.garbage_interval();
+        }
+    }
+
+    fn apply_gravity(&mut self, timing: Timing) {
+        self.gravity_progress += timing.gravity;
+
+        while self.gravity_progress >= 256 {
+            self.gravity_progress -= 256;
+            if self.fall().is_err() {
+                self.gravity_progress = 0;
+                break;
+            }
+        }
+
+        if self.is_grounded() {
+            self.lock_frames += 1;
+            if self.lock_frames >= timing.lock_delay { self.lock() }
+        }
+    }
+
+    fn is_grounded(&self) -> bool {
+        match self.current {
+            None => false,
+            Some((tetro, pos)) => !self.fit_test(tetro, Pos2d{ x: pos.x, y: pos.y + 1 }),
+        }
+    }
+
+    /// Puts the current piece into the grid and starts the line clear or entry delay.
+    pub fn lock(&mut self) {
+        if let Some((tetro, pos)) = self.current {
+            let grid = self.grid;
+            let tspin = self.is_tspin(tetro, pos);
+            for tile in tetro.get_tiles() {
+                self.grid[(tile.y as isize + pos.y) as usize][(tile.x as isize + pos.x) as usize] = Some(Cell { color: tetro.color, locked_at: self.ticks });
+            }
+
+            let lines = self.grid.iter().filter(|line| line.iter().all(|cell| cell.is_some())).count();
+            let perfect_clear = lines > 0 && self.grid.iter().all(|line| line.iter().all(|cell| cell.is_some()) || line.iter().all(|cell| cell.is_none()));
+            let lock = Lock { tetromino: tetro, pos, inputs: std::mem::take(&mut self.inputs), grid, lines, tspin };
+            self.stats.record(&lock, perfect_clear);
+            self.last_lock = Some(lock);
+        }
+
+        self.current = None;
+        self.pieces += 1;
+        self.hold_used = false;
+        self.lock_frames = 0;
+        self.gravity_progress = 0;
+
+        let timing = self.timing();
+        let any_full = self.grid.iter().any(|line| line.iter().all(|cell| cell.is_some()));
 
-                self.spawn()
+        if any_full && timing.line_clear > 0 {
+            self.phase = Phase::LineClear(timing.line_clear);
+        } else {
+            self.remove_lines();
+            self.phase = Phase::Entry(timing.are);
+        }
+    }
+
+    pub fn apply(&mut self, action: util::Action) {
+        // the keys of the other inputs are counted once the piece locks or is held, when runs of them are known
+        if self.current.is_some() {
+            if action == util::Action::Hold { self.stats.keys += 1 } else { self.inputs.push(action) }
+        }
+
+        match action {
+            util::Action::MoveLeft => self.move_left(),
+            util::Action::MoveRight => self.move_right(),
+            util::Action::RotateClockwise => self.rotate(util::RotDirection::Clockwise),
+            util::Action::RotateCounterClockwise => self.rotate(util::RotDirection::CounterClockwise),
+            util::Action::SoftDrop => self.soft_drop(),
+            util::Action::HardDrop => self.hard_drop(),
+            util::Action::Hold => self.hold(),
+        }
+    }
+
+    /// Stable 64 bit hash (FNV-1a) of everything that decides how the game continues.
+    ///
+    /// Unlike `std::hash` the value does not change between builds or platforms,
+    /// so hash streams of two runs can be compared to find where they diverge.
+    pub fn hash(&self) -> u64 {
+        let mut hash = FNV_OFFSET;
+        let mut write = |value: u64| {
+            for byte in value.to_le_bytes() {
+                hash = (hash ^ byte as u64).wrapping_mul(FNV_PRIME);
+            }
+        };
+
+        for cell in self.grid.iter().flatten() {
+            write(cell.map_or(0, |cell| cell.color as u64 + 1));
+        }
+
+        match self.current {
+            Some((tetro, pos)) => {
+                write(tetro.shape.to_char() as u64);
+                write(tetro.orientation as u64);
+                write(pos.x as u64);
+                write(pos.y as u64);
             },
+            None => write(0),
+        }
+
+        write(self.hold.map_or(0, |shape| shape.to_char() as u64));
+        write(self.hold_used as u64);
+        write(self.next.len() as u64);
+        for shape in self.next.iter() {
+            write(shape.to_char() as u64);
         }
 
-        self.remove_lines();
+        write(self.randomizer.seed);
+        write(self.randomizer.draws);
+        // only with a sequence, so hash streams of games without one stay the same
+        if let Some(sequence) = self.sequence.as_ref() { write(sequence.is_finished() as u64) }
+        write(self.score as u64);
+        write(self.pieces as u64);
+        write(self.level as u64);
+        write(self.ticks as u64);
+        write(self.state as u64);
+        match self.phase {
+            Phase::Entry(frames) => { write(0); write(frames as u64) },
+            Phase::Active => write(1),
+            Phase::LineClear(frames) => { write(2); write(frames as u64) },
+        }
+        write(self.next_garbage as u64);
+        write(self.gravity_progress as u64);
+        write(self.lock_frames as u64);
 
-        if self.current.is_none() { self.spawn() }
+        hash
     }
 
-    pub fn fall(&mut self) -> Result<(), ()> {
+    /// Drops the current piece as far as it goes, it locks once the lock delay ran out.
+    pub fn hard_drop(&mut self) {
+        if self.current.is_none() { return }
+        while self.fall().is_ok() {}
+    }
+
+    /// Moves the current piece down by one row. In master mode a grounded piece locks immediately.
+    pub fn soft_drop(&mut self) {
+        if self.fall().is_err() && self.mode == Mode::Master {
+            self.lock();
+        }
+    }
+
+    /// Pushes `rows` garbage lines into the bottom of the grid, each with a hole in `hole_column`.
+    /// The stack and the falling piece are shifted up; anything pushed out of the grid tops the player out.
+    /// A column past the right wall puts the hole in the rightmost column.
+    pub fn insert_garbage(&mut self, rows: usize, hole_column: usize) {
+        let rows = rows.min(22);
+        let hole_column = hole_column.min(9);
+
+        if self.grid[..rows].iter().any(|line| line.iter().any(|cell| cell.is_some())) {
+            self.state = State::Lost;
+        }
+
+        for y in 0..22 - rows {
+            self.grid[y] = self.grid[y + rows];
+        }
+        for line in self.grid[22 - rows..].iter_mut() {
+            *line = [Some(Cell { color: Color::Gray, locked_at: self.ticks }); 10];
+            line[hole_column] = None;
+        }
+
+        if let Some((tetro, mut pos)) = self.current {
+            while !self.fit_test(tetro, pos) {
+                if tetro.get_tiles().iter().any(|tile| tile.y as isize + pos.y <= 0) {
+                    self.state = State::Lost;
+                    break;
+                }
+                pos.y -= 1;
+            }
+            self.current = Some((tetro, pos));
+        }
+    }
+
+    /// frames between two garbage rows in survival, starting at 10s and shrinking to 2s
+    fn garbage_interval(&self) -> usize {
+        300usize.saturating_sub(self.ticks / 150).max(60)
+    }
+
+    pub(crate) fn fall(&mut self) -> Result<(), ()> {
         match self.current {
             None => Ok(()),
             Some((tetro, pos)) => {
@@ -69,6 +462,7 @@ impl GameState {
 
                 if test {
                     self.current = Some((tetro, Pos2d{ x: pos.x, y: pos.y + 1 }));
+                    self.lock_frames = 0;
                     Ok(())
                 } else {
                     Err(())
@@ -100,31 +494,53 @@ impl GameState {
     pub fn rotate(&mut self, direction: util::RotDirection) {
         match self.current {
             None => (),
-            Some((mut tetro, pos)) => {
-                let offsets = tetromino::get_shape_offset_tests(tetro.shape, tetro.orientation, direction);
-                tetro.rotate(direction);
-
-                for offset in offsets.into_iter() {
-                    let test = self.fit_test(tetro, Pos2d { x: pos.x + offset.x, y: pos.y + offset.y });
-                    if test {
-                        self.current = Some((tetro, Pos2d { x: pos.x + offset.x, y: pos.y + offset.y }));
-                        return;
-                    }
-                }
+            Some((tetro, pos)) => {
+                if let Some(rotated) = self.rotated(tetro, pos, direction) { self.current = Some(rotated) }
             },
         }
     }
 
+    /// Whether a T at `pos` is a T-spin: three of the corners around its center are filled
+    /// and it can not move sideways or up, so it could only have been rotated in.
+    fn is_tspin(&self, tetro: Tetromino, pos: Pos2d) -> bool {
+        if tetro.shape != tetromino::Shape::T { return false }
+
+        // the walls and floor count as filled
+        let filled = |x: isize, y: isize| !(0..10).contains(&x) || y >= 22 || (y >= 0 && self.grid[y as usize][x as usize].is_some());
+        let corners = [(0, 0), (2, 0), (0, 2), (2, 2)].into_iter().filter(|&(x, y)| filled(pos.x + x, pos.y + y)).count();
+        let stuck = [(-1, 0), (1, 0), (0, -1)].into_iter().all(|(x, y)| !self.fit_test(tetro, Pos2d { x: pos.x + x, y: pos.y + y }));
+
+        corners >= 3 && stuck
+    }
+
+    /// Where `tetro` at `pos` ends up when rotated, trying the offsets in order, `None` if none fits.
+    pub fn rotated(&self, mut tetro: Tetromino, pos: Pos2d, direction: util::RotDirection) -> Option<(Tetromino, Pos2d)> {
+        let offsets = tetromino::get_shape_offset_tests(tetro.shape, tetro.orientation, direction);
+        tetro.rotate(direction);
+
+        for offset in offsets.into_iter() {
+            let test = self.fit_test(tetro, Pos2d { x: pos.x + offset.x, y: pos.y + offset.y });
+            if test {
+                return Some((tetro, Pos2d { x: pos.x + offset.x, y: pos.y + offset.y }));
+            }
+        }
+
+        None
+    }
+
     fn remove_lines(&mut self) {
         let mut count = 0;
 
         for (y, &line) in self.grid.clone().iter().enumerate() {
-            let blocks_in_line = line.into_iter().filter(|&cell| { cell.is_some() }).collect::<Vec<Option<Color>>>().len();
+            let blocks_in_line = line.into_iter().filter(|&cell| { cell.is_some() }).collect::<Vec<Option<Cell>>>().len();
             let full = blocks_in_line == 10;
             
             if blocks_in_line != 0 {
                 if y == 0 || y == 1 {
-                    self.state = State::Lost;
+                    match self.mode {
+                        Mode::Zen { .. } => self.grid = [[None; 10]; 22],
+                        _ => self.state = State::Lost,
+                    }
                     return;
                 }
             }
@@ -146,9 +562,14 @@ impl GameState {
             4 => 800,
             _ => 0,
         };
+
+        if self.mode == Mode::Master {
+            self.level = (self.level + count).min(timing::MASTER_MAX_LEVEL);
+            if self.level == timing::MASTER_MAX_LEVEL { self.state = State::Won }
+        }
     }
 
-    fn fit_test(&self, tetro: Tetromino, pos: util::Pos2d) -> bool {
+    pub fn fit_test(&self, tetro: Tetromino, pos: util::Pos2d) -> bool {
         for tile in tetro.get_tiles() {
             if (tile.x as isize + pos.x) > 9 { return false }
             if (tile.x as isize + pos.x) < 0 { return false }
@@ -162,7 +583,7 @@ impl GameState {
 
 impl Display for GameState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut dsp_grid = self.grid.clone();
+        let mut dsp_grid = self.grid.map(|line| line.map(|cell| cell.map(|cell| cell.color)));
 
         if let Some((tetro, pos)) = self.current {
             for tile in tetro.get_tiles() {
@@ -182,6 +603,7 @@ impl Display for GameState {
                         Color::Green => "{}".to_string(),
                         Color::Purple => "▓▓".to_string(),
                         Color::Red => "░░".to_string(),
+                        Color::Gray => "##".to_string(),
                     },
 
                 }
@@ -191,3 +613,27 @@ impl Display for GameState {
         write!(f,"GameState:\n{}" , out)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn holding_the_last_piece_does_not_end_a_sequence() {
+        let mut g = GameState::with_sequence(Mode::Zen { gravity: 0 }, Visibility::Normal, Sequence::parse("IO").unwrap());
+        g.tick();
+        g.apply(util::Action::HardDrop);
+        g.lock();
+        g.tick();
+
+        g.apply(util::Action::Hold);
+        assert!(g.state == State::Running);
+        assert!(g.hold.is_none() && !g.hold_used);
+        assert!(g.current.is_some_and(|(tetro, _)| tetro.shape == tetromino::Shape::O));
+
+        g.apply(util::Action::HardDrop);
+        g.lock();
+        g.tick();
+        assert!(g.state == State::Won);
+    }
+}
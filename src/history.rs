@@ -0,0 +1,65 @@
+use std::collections::VecDeque;
+
+use crate::gamestate::GameState;
+
+/// placements that can be undone at most
+const HISTORY_LIMIT: usize = 100;
+
+/// Undo and redo of placements, for drilling positions in practice.
+///
+/// Every entry is the game as it was when a piece entered the field,
+/// so undoing puts the last placed piece back on top to be placed again.
+#[derive(Default)]
+pub struct History {
+    undo: VecDeque<GameState>,
+    redo: Vec<GameState>,
+    /// the game when the current piece entered, `None` between a lock and the next spawn
+    placement: Option<GameState>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self { undo: VecDeque::new(), redo: Vec::new(), placement: None }
+    }
+
+    /// Notes locks and spawns, to be called once per frame after the game advanced.
+    pub fn update(&mut self, gamestate: &GameState) {
+        if let Some(placement) = self.placement.as_ref() {
+            if gamestate.pieces != placement.pieces {
+                if self.undo.len() == HISTORY_LIMIT { self.undo.pop_front(); }
+                self.undo.extend(self.placement.take());
+                self.redo.clear();
+            }
+        }
+
+        if self.placement.is_none() && gamestate.current.is_some() {
+            self.placement = Some(gamestate.clone());
+        }
+    }
+
+    /// Takes back the last placement, returns whether there was one.
+    pub fn undo(&mut self, gamestate: &mut GameState) -> bool {
+        let previous = match self.undo.pop_back() {
+            Some(previous) => previous,
+            None => return false,
+        };
+
+        self.redo.push(self.placement.take().unwrap_or_else(|| gamestate.clone()));
+        *gamestate = previous.clone();
+        self.placement = Some(previous);
+        true
+    }
+
+    /// Places the last undone piece again, returns whether there was one.
+    pub fn redo(&mut self, gamestate: &mut GameState) -> bool {
+        let next = match self.redo.pop() {
+            Some(next) => next,
+            None => return false,
+        };
+
+        self.undo.extend(self.placement.take());
+        *gamestate = next.clone();
+        self.placement = Some(next);
+        true
+    }
+}
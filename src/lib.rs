@@ -0,0 +1,25 @@
+pub mod util;
+pub mod tetromino;
+pub mod timing;
+pub mod gamestate;
+pub mod randomizer;
+pub mod board;
+pub mod fumen;
+pub mod replay;
+pub mod history;
+pub mod movegen;
+pub mod eval;
+pub mod bot;
+pub mod pc;
+pub mod finesse;
+pub mod drill;
+pub mod opener;
+pub mod puzzle;
+pub mod stats;
+pub mod profile;
+pub mod scores;
+pub mod analytics;
+pub mod versus;
+
+/// frames per second the game runs at, all timings are in frames
+pub const FPS: u64 = 30;
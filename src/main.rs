@@ -1,53 +1,831 @@
 
-mod util;
-mod tetromino;
-mod gamestate;
+use tetris::{analytics, board, bot, drill, eval, finesse, fumen, gamestate, history, movegen, opener, pc, profile, puzzle, randomizer, replay, scores, util, versus, FPS};
+
+mod editor;
 mod rendering;
 
+const MILLISPF: u64 = 1000 / FPS;
+/// perfect clear solutions printed at most
+const PC_SOLUTIONS: usize = 5;
+
 fn main() {
-    let fps = 30;
-    let millispf = 1000 / fps;
+    let args: Vec<String> = std::env::args().skip(1).collect();
 
-    let mut g = gamestate::GameState::new();
+    let opener = match args.iter().find_map(|arg| arg.strip_prefix("--opener=")) {
+        Some(name) => match opener::Opener::load(&get_library_path(name, "openers")) {
+            Ok(opener) => Some(opener),
+            Err(e) => { eprintln!("{}", e); return },
+        },
+        None => None,
+    };
+
+    // drilling placements and practicing openers happens without gravity
+    let drilling = args.iter().any(|arg| arg == "--drill");
+
+    let mode = match args.iter().find(|arg| !arg.starts_with("--")).map(|arg| arg.as_str()) {
+        _ if drilling || opener.is_some() => gamestate::Mode::Zen { gravity: 0 },
+        Some("survival") => gamestate::Mode::Survival,
+        Some("master") => gamestate::Mode::Master,
+        Some("zen") => {
+            let gravity = args.iter()
+                .find_map(|arg| arg.strip_prefix("--gravity="))
+                .and_then(|gravity| gravity.parse().ok())
+                .unwrap_or(0);
+            gamestate::Mode::Zen { gravity }
+        },
+        _ => gamestate::Mode::Marathon,
+    };
+
+    let visibility = if args.iter().any(|arg| arg == "--invisible") {
+        gamestate::Visibility::Invisible
+    } else if args.iter().any(|arg| arg == "--fading") {
+        gamestate::Visibility::Fading
+    } else {
+        gamestate::Visibility::Normal
+    };
+
+    // a fixed piece order, given directly or as a file
+    let sequence = match args.iter().find_map(|arg| arg.strip_prefix("--sequence=")) {
+        Some(text) if std::path::Path::new(text).exists() => randomizer::Sequence::load(text).map(Some),
+        Some(text) => randomizer::Sequence::parse(text).map(Some),
+        None => Ok(None),
+    };
+
+    let mut g = match sequence {
+        Ok(Some(sequence)) => gamestate::GameState::with_sequence(mode, visibility, sequence),
+        Ok(None) => gamestate::GameState::new(mode, visibility),
+        Err(e) => { eprintln!("{}", e); return },
+    };
+
+    let mut weights = Vec::new();
+    for path in args.iter().filter_map(|arg| arg.strip_prefix("--weights=")) {
+        match eval::Weights::load(path) {
+            Ok(loaded) => weights.push(loaded),
+            Err(e) => { eprintln!("{}", e); return },
+        }
+    }
+    if weights.is_empty() { weights.push(eval::Weights::default()) }
+
+    if let Some(data) = args.iter().find_map(|arg| arg.strip_prefix("--evaluate=")) {
+        evaluate(data, &weights);
+        return;
+    }
+
+    if let Some(path) = args.iter().find_map(|arg| arg.strip_prefix("--verify=")) {
+        if !verify(path, args.iter().find_map(|arg| arg.strip_prefix("--hashes="))) { std::process::exit(1) }
+        return;
+    }
 
-    let mut frame: usize = 0;
     let mut renderer: Box<dyn rendering::Renderer> = Box::new(rendering::SdlRenderer::new());
 
+    let profile_name = args.iter().find_map(|arg| arg.strip_prefix("--profile=")).unwrap_or("default");
+    if let Err(e) = profile::Profile::path(profile_name) { eprintln!("{}", e); return }
+    if args.iter().any(|arg| arg == "--history") {
+        match profile::Profile::load(profile_name) {
+            Ok(profile) => show_history(renderer.as_mut(), &profile),
+            Err(e) => eprintln!("{}", e),
+        }
+        return;
+    }
+
+    if args.iter().any(|arg| arg == "--scores") {
+        let (scores, e) = scores::Scores::load_or_recover(scores::SCORES_PATH);
+        if let Some(e) = e { eprintln!("{}", e) }
+        show_high_scores(renderer.as_mut(), &scores, mode, visibility);
+        return;
+    }
+
+    let board_path = args.iter().find_map(|arg| arg.strip_prefix("--board="));
+    let edit = args.iter().any(|arg| arg == "--edit");
+
+    let mut board = match board_path {
+        Some(path) if !edit || std::path::Path::new(path).exists() => match board::Board::load(path) {
+            Ok(board) => board,
+            Err(e) => { eprintln!("{}", e); return },
+        },
+        _ => board::Board::new(),
+    };
+
+    if edit {
+        if !editor::run(renderer.as_mut(), &mut board) { return }
+
+        if let Some(path) = board_path {
+            if let Err(e) = board.save(path) { eprintln!("{}", e) }
+        }
+    }
+
+    board.apply(&mut g);
+
+    let fumen_data = args.iter().find_map(|arg| arg.strip_prefix("--fumen="));
+    if let Some(data) = fumen_data {
+        let page: usize = args.iter()
+            .find_map(|arg| arg.strip_prefix("--page="))
+            .and_then(|page| page.parse().ok())
+            .unwrap_or(1);
+
+        match fumen::decode(data) {
+            Ok(pages) => match pages.get(page.saturating_sub(1)) {
+                Some(page) => page.apply(&mut g),
+                None => { eprintln!("the fumen has only {} pages", pages.len()); return },
+            },
+            Err(e) => { eprintln!("{}", e); return },
+        }
+    }
+
+    if let Some(pieces) = args.iter().find_map(|arg| arg.strip_prefix("--pc=")) {
+        let pieces = pieces.parse().unwrap_or(10);
+        let solutions = pc::solve(&g, pieces, PC_SOLUTIONS);
+        if solutions.is_empty() { println!("no perfect clear within {} pieces", pieces) }
+        for solution in solutions {
+            println!("{}", pc::to_fumen(&g, &solution));
+        }
+        return;
+    }
+
+    if let Some(name) = args.iter().find_map(|arg| arg.strip_prefix("--puzzle=")) {
+        let path = get_library_path(name, "puzzles");
+
+        // a directory is a pack of puzzles, played in the order of their file names
+        let paths = match std::fs::read_dir(&path) {
+            Ok(entries) => {
+                let mut paths: Vec<String> = entries.flatten().map(|entry| entry.path().to_string_lossy().into_owned()).collect();
+                paths.sort();
+                paths
+            },
+            Err(_) => vec![path],
+        };
+
+        let mut puzzles = Vec::new();
+        for path in paths {
+            match puzzle::Puzzle::load(&path) {
+                Ok(puzzle) => puzzles.push(puzzle),
+                Err(e) => { eprintln!("{}", e); return },
+            }
+        }
+
+        solve(renderer.as_mut(), &puzzles);
+        return;
+    }
+
+    let analyze: Vec<&str> = args.iter().filter_map(|arg| arg.strip_prefix("--analyze=")).collect();
+    if !analyze.is_empty() {
+        let mut analytics = analytics::Analytics::new();
+        for path in analyze {
+            match replay::Replay::load(path) {
+                Ok(replay) => analytics.add_replay(&replay),
+                Err(e) => { eprintln!("{}", e); return },
+            }
+        }
+
+        if let Some(prefix) = args.iter().find_map(|arg| arg.strip_prefix("--csv=")) {
+            if let Err(e) = analytics.save_csv(prefix) { eprintln!("{}", e) }
+        }
+
+        show_analytics(renderer.as_mut(), &analytics);
+        return;
+    }
+
+    if let Some(path) = args.iter().find_map(|arg| arg.strip_prefix("--replay=")) {
+        let mut playback = match replay::Replay::load(path) {
+            Ok(replay) => replay::Playback::new(replay),
+            Err(e) => { eprintln!("{}", e); return },
+        };
+
+        watch(renderer.as_mut(), &mut playback);
+        return;
+    }
+
+    let save_path = args.iter().find_map(|arg| arg.strip_prefix("--save="));
+    let resumed = match save_path {
+        Some(path) if std::path::Path::new(path).exists() => match gamestate::GameState::load(path) {
+            Ok(saved) => { g = saved; true },
+            Err(e) => { eprintln!("{}", e); return },
+        },
+        _ => false,
+    };
+
+    let sparring = args.iter().any(|arg| arg == "--versus");
+    if sparring && save_path.is_some() {
+        eprintln!("a versus game can not be suspended");
+        return;
+    }
+
+    let mut record_path = args.iter().find_map(|arg| arg.strip_prefix("--record="));
+    if resumed && record_path.is_some() {
+        eprintln!("a resumed game can not be recorded");
+        record_path = None;
+    }
+    if sparring && record_path.is_some() {
+        eprintln!("a versus game can not be recorded, the garbage it receives is not part of a replay");
+        record_path = None;
+    }
+    if drilling && record_path.is_some() {
+        eprintln!("a drill can not be recorded, clearing the board after each placement is not part of a replay");
+        record_path = None;
+    }
+    if opener.is_some() && record_path.is_some() {
+        eprintln!("opener practice can not be recorded, restarting the opener is not part of a replay");
+        record_path = None;
+    }
+    let mut recording = record_path.map(|_| replay::Replay::new(&g));
+
+    let get_bot = || {
+        let lookahead = args.iter()
+            .find_map(|arg| arg.strip_prefix("--lookahead="))
+            .and_then(|lookahead| lookahead.parse().ok())
+            .unwrap_or(1);
+        let pps = args.iter()
+            .find_map(|arg| arg.strip_prefix("--pps="))
+            .and_then(|pps| pps.parse().ok())
+            .unwrap_or(2.0);
+        bot::Bot::new(weights[0], lookahead, pps)
+    };
+
+    // the bot plays instead of the player, as a demo
+    let mut bot = args.iter().any(|arg| arg == "--bot").then(get_bot);
+
+    let health = args.iter().any(|arg| arg == "--health").then_some(&weights[0]);
+    let mut finesse = args.iter().any(|arg| arg == "--finesse").then(finesse::Finesse::new);
+
+    let mut guide = match opener {
+        _ if drilling => Some(Guide::Drill(drill::Drill::new())),
+        // or against the player, trading garbage
+        _ if sparring => Some(Guide::Versus(Box::new(versus::Versus::new(&g, get_bot())))),
+        Some(opener) => Some(Guide::Opener(opener::Practice::new(opener))),
+        None => None,
+    };
+    if let Some(Guide::Opener(practice)) = guide.as_mut() { practice.start(&mut g) }
+
+    let quit = play(renderer.as_mut(), &mut g, recording.as_mut(), bot.as_mut(), health, finesse.as_mut(), guide.as_mut());
+
+    for line in g.stats.summary() { println!("{}", line) }
+
+    // a zen game only ends by quitting, unless it is suspended to be resumed
+    let finished = g.state != gamestate::State::Running || (matches!(g.mode, gamestate::Mode::Zen { .. }) && save_path.is_none());
+    if finished && g.pieces > 0 && bot.is_none() && guide.is_none() {
+        let result = profile::Profile::load(profile_name).and_then(|mut profile| {
+            profile.games.push(profile::Game::from_gamestate(&g));
+            profile.save()
+        });
+        if let Err(e) = result { eprintln!("{}", e) }
+    }
+
+    // only games that ended on their own and were dealt the usual pieces on an empty field make the high scores
+    let ranked = g.state != gamestate::State::Running && bot.is_none() && guide.is_none() && g.sequence.is_none() && board_path.is_none() && fumen_data.is_none();
+
+    match guide {
+        Some(Guide::Drill(drill)) => print_drill(&drill),
+        Some(Guide::Opener(practice)) => println!("{} completed {} times, {} mistakes", practice.opener.name, practice.completed, practice.mistakes),
+        Some(Guide::Versus(versus)) if versus.is_draw(&g) => println!("drew against the bot"),
+        Some(Guide::Versus(_)) => println!("{} against the bot", if g.state == gamestate::State::Won { "won" } else { "lost" }),
+        Some(Guide::Puzzle(_)) | None => {},
+    }
+
+    // quitting a running game suspends it, a finished one has nothing left to resume
+    if let Some(path) = save_path {
+        let result = if quit && g.state == gamestate::State::Running {
+            g.save(path)
+        } else if resumed {
+            std::fs::remove_file(path).map_err(|e| format!("{}: {}", path, e))
+        } else {
+            Ok(())
+        };
+        if let Err(e) = result { eprintln!("{}", e) }
+    }
+
+    if let (Some(path), Some(recording)) = (record_path, recording.as_mut()) {
+        recording.finish(&g);
+        if let Err(e) = recording.save(path) { eprintln!("{}", e) }
+    }
+
+    if quit { return }
+
+    if ranked {
+        show_high_score(renderer.as_mut(), &mut g, profile_name);
+    } else {
+        show_final_board(renderer.as_mut(), &mut g);
+    }
+}
+
+/// Prints the features of every page of a fumen and its evaluation by each of the weights.
+fn evaluate(data: &str, weights: &[eval::Weights]) {
+    let pages = match fumen::decode(data) {
+        Ok(pages) => pages,
+        Err(e) => { eprintln!("{}", e); return },
+    };
+
+    println!("page height max_height holes row_transitions column_transitions wells bumpiness tspin_slots {}",
+        (1..=weights.len()).map(|i| format!("eval{}", i)).collect::<Vec<String>>().join(" "));
+
+    for (i, page) in pages.iter().enumerate() {
+        let grid = page.grid.map(|line| line.map(|cell| cell.map(|color| gamestate::Cell { color, locked_at: 0 })));
+        let features = eval::Features::from_grid(&grid);
+
+        println!("{} {} {} {} {} {} {} {} {} {}",
+            i + 1, features.height, features.max_height, features.holes, features.row_transitions,
+            features.column_transitions, features.wells, features.bumpiness, features.tspin_slots,
+            weights.iter().map(|weights| format!("{:.3}", weights.evaluate(&features))).collect::<Vec<String>>().join(" "));
+    }
+}
+
+/// Prints every placement of the current piece as fumen, a page each with its inputs as comment.
+fn print_placements(g: &gamestate::GameState) {
+    let shape = match g.current {
+        Some((tetro, _)) => tetro.shape,
+        None => return,
+    };
+
+    let pages: Vec<fumen::Page> = movegen::placements(g, shape).into_iter().map(|placement| fumen::Page {
+        piece: Some((placement.tetromino, placement.pos)),
+        comment: placement.inputs.iter().map(|input| input.to_string()).collect::<Vec<String>>().join(" "),
+        ..fumen::Page::from_gamestate(g)
+    }).collect();
+
+    println!("{}", fumen::encode(&pages));
+}
+
+/// A file name, or the name of a file in one of the libraries under res.
+fn get_library_path(name: &str, library: &str) -> String {
+    if std::path::Path::new(name).exists() { name.to_string() } else { format!("./res/{}/{}.txt", library, name) }
+}
+
+/// Plays through puzzles, a solved one moves on to the next and a failed one is tried again.
+fn solve(renderer: &mut dyn rendering::Renderer, puzzles: &[puzzle::Puzzle]) {
+    let mut index = 0;
+
+    while let Some(puzzle) = puzzles.get(index) {
+        let mut g = puzzle.start();
+        let mut guide = Guide::Puzzle(puzzle::Mission::new(puzzle.clone()));
+        if play(renderer, &mut g, None, None, None, None, Some(&mut guide)) { break }
+
+        let solved = matches!(guide, Guide::Puzzle(puzzle::Mission { result: Some(true), .. }));
+        renderer.set_overlay(vec![
+            format!("{} {} of {}", if solved { "solved" } else { "failed" }, index + 1, puzzles.len()),
+            format!("space: {}", if solved { "next" } else { "retry" }),
+        ]);
+        if show_final_board(renderer, &mut g) { break }
+
+        if solved { index += 1 }
+    }
+
+    renderer.set_overlay(Vec::new());
+}
+
+/// Shows the personal bests and recent trend of every mode the player played until they quit or press space.
+fn show_history(renderer: &mut dyn rendering::Renderer, profile: &profile::Profile) {
+    let mut overlay = vec![format!("{}: {} games", profile.name, profile.games.len())];
+
+    for summary in profile.summaries() {
+        overlay.push(String::new());
+        overlay.push(format!("{}: {} games", summary.mode, summary.games));
+        overlay.push(format!("best {} on {}", summary.best.score, summary.best.day()));
+        overlay.push(format!("most lines {}", summary.most_lines));
+        overlay.push(format!("best pps {:.2}", summary.best_pps));
+        overlay.push(match summary.recent {
+            (recent, Some(before)) if before > 0.0 => format!("recent avg {:.0} ({:+.0}%)", recent, (recent / before - 1.0) * 100.0),
+            (recent, _) => format!("recent avg {:.0}", recent),
+        });
+    }
+
+    renderer.set_overlay(overlay);
+    show_final_board(renderer, &mut gamestate::GameState::new(gamestate::Mode::Marathon, gamestate::Visibility::Normal));
+    renderer.set_overlay(Vec::new());
+}
+
+/// Shows where pieces were placed in the analysed games until the player quits or presses space.
+///
+/// Left and right switch between all pieces and each shape on its own.
+fn show_analytics(renderer: &mut dyn rendering::Renderer, analytics: &analytics::Analytics) {
+    let shapes: Vec<_> = std::iter::once(None).chain(analytics::SHAPES.map(Some)).collect();
+    let mut g = gamestate::GameState::new(gamestate::Mode::Zen { gravity: 0 }, gamestate::Visibility::Normal);
+    let mut index = 0;
+
+    loop {
+        let events = renderer.get_events();
+        if events.contains(&util::Event::Quit) || events.contains(&util::Event::KeyDown(util::Keycode::Space)) { break }
+        if events.contains(&util::Event::KeyDown(util::Keycode::Right)) { index = (index + 1) % shapes.len() }
+        if events.contains(&util::Event::KeyDown(util::Keycode::Left)) { index = (index + shapes.len() - 1) % shapes.len() }
+
+        let shape = shapes[index];
+        let cells = analytics.cells(shape);
+        let most = cells.iter().flatten().copied().max().unwrap_or(0).max(1);
+        renderer.set_heatmap(Some(cells.map(|row| row.map(|count| count as f32 / most as f32))));
+
+        let orientations = analytics.orientations(shape);
+        let placements: usize = orientations.iter().map(|&(_, count)| count).sum();
+
+        let mut overlay = vec![match shape {
+            Some(shape) => format!("{} pieces: {}", shape.to_char(), placements),
+            None => format!("all pieces: {} in {} games", placements, analytics.games),
+        }];
+        for (orientation, count) in orientations {
+            overlay.push(format!("{} {:.0}%", orientation, count as f64 * 100.0 / placements.max(1) as f64));
+        }
+        overlay.push(format!("holes made by {:.1}%", analytics.hole_rate(shape) * 100.0));
+        overlay.push("height over a game".to_string());
+        overlay.push(analytics.heights(10).iter().map(|height| format!("{:.0}", height)).collect::<Vec<_>>().join(" "));
+        renderer.set_overlay(overlay);
+
+        renderer.draw(&mut g);
+
+        std::thread::sleep(std::time::Duration::from_millis(MILLISPF));
+    }
+
+    renderer.set_heatmap(None);
+    renderer.set_overlay(Vec::new());
+}
+
+/// Enters a finished game into the high scores of its mode and visibility if it qualifies, asking for the name to list it under,
+/// then shows the table over the final board. Returns whether the player quit, which skips the entry.
+fn show_high_score(renderer: &mut dyn rendering::Renderer, g: &mut gamestate::GameState, name: &str) -> bool {
+    let (mut scores, e) = scores::Scores::load_or_recover(scores::SCORES_PATH);
+    if let Some(e) = e { eprintln!("{}", e) }
+
+    let mut rank = None;
+    if scores.qualifies(g.mode, g.visibility, g.score) {
+        let name = match enter_name(renderer, g, name) {
+            Some(name) => name,
+            None => return true,
+        };
+
+        rank = scores.insert(g.mode, g.visibility, scores::Entry::new(&name, g.score, g.stats.lines()));
+        if let Err(e) = scores.save(scores::SCORES_PATH) { eprintln!("{}", e) }
+    }
+
+    renderer.set_overlay(get_score_overlay(&scores, g.mode, g.visibility, rank));
+    let quit = show_final_board(renderer, g);
+    renderer.set_overlay(Vec::new());
+    quit
+}
+
+/// Asks for a name over the final board, starting out with `name`, returns `None` if the player quit.
+fn enter_name(renderer: &mut dyn rendering::Renderer, g: &mut gamestate::GameState, name: &str) -> Option<String> {
+    let mut name: String = name.chars().take(scores::NAME_LENGTH).collect();
+
+    loop {
+        for event in renderer.get_events() {
+            match event {
+                util::Event::Quit => return None,
+                util::Event::KeyDown(util::Keycode::Return) if !name.trim().is_empty() => return Some(name.trim().to_string()),
+                util::Event::KeyDown(util::Keycode::Backspace) => { name.pop(); },
+                util::Event::Text(c) if !c.is_control() && name.chars().count() < scores::NAME_LENGTH => name.push(c),
+                _ => {},
+            }
+        }
+
+        renderer.set_overlay(vec![
+            "new high score!".to_string(),
+            format!("name: {}_", name),
+            "enter to save".to_string(),
+        ]);
+        renderer.draw(g);
+
+        std::thread::sleep(std::time::Duration::from_millis(MILLISPF));
+    }
+}
+
+/// Browses the high scores of all modes, starting with `mode` and `visibility`, until the player quits or presses space.
+///
+/// Left and right switch between the tables.
+fn show_high_scores(renderer: &mut dyn rendering::Renderer, scores: &scores::Scores, mode: gamestate::Mode, visibility: gamestate::Visibility) {
+    let mut g = gamestate::GameState::new(mode, gamestate::Visibility::Normal);
+    let mut index = scores.tables.iter().position(|table| table.mode == mode && table.visibility == visibility).unwrap_or(0);
+
+    loop {
+        let events = renderer.get_events();
+        if events.contains(&util::Event::Quit) || events.contains(&util::Event::KeyDown(util::Keycode::Space)) { break }
+        if events.contains(&util::Event::KeyDown(util::Keycode::Right)) { index += 1 }
+        if events.contains(&util::Event::KeyDown(util::Keycode::Left)) { index += scores.tables.len().max(1) - 1 }
+        index %= scores.tables.len().max(1);
+
+        let overlay = match scores.tables.get(index) {
+            Some(table) => get_score_overlay(scores, table.mode, table.visibility, None),
+            None => vec!["no high scores yet".to_string()],
+        };
+        renderer.set_overlay(overlay);
+        renderer.draw(&mut g);
+
+        std::thread::sleep(std::time::Duration::from_millis(MILLISPF));
+    }
+
+    renderer.set_overlay(Vec::new());
+}
+
+/// the high score table of a mode and visibility, marking the entry at `rank`
+fn get_score_overlay(scores: &scores::Scores, mode: gamestate::Mode, visibility: gamestate::Visibility, rank: Option<usize>) -> Vec<String> {
+    let mut overlay = vec![match visibility {
+        gamestate::Visibility::Normal => format!("{} high scores", mode),
+        _ => format!("{} {} high scores", mode, visibility),
+    }];
+
+    for (i, entry) in scores.table(mode, visibility).iter().enumerate() {
+        let marker = if rank == Some(i) { ">" } else { " " };
+        overlay.push(format!("{}{:>2} {:<width$} {}", marker, i + 1, entry.name, entry.score, width = scores::NAME_LENGTH));
+    }
+
+    overlay
+}
+
+/// Practice that shows the player where the current piece should go, or what to achieve,
+/// or the bot as a sparring opponent.
+enum Guide {
+    Drill(drill::Drill),
+    Opener(opener::Practice),
+    Puzzle(puzzle::Mission),
+    Versus(Box<versus::Versus>),
+}
+
+impl Guide {
+    fn update(&mut self, g: &mut gamestate::GameState) {
+        match self {
+            Guide::Drill(drill) => drill.update(g),
+            Guide::Opener(practice) => practice.update(g),
+            Guide::Puzzle(mission) => mission.update(g),
+            Guide::Versus(versus) => versus.update(g),
+        }
+    }
+
+    fn target(&self) -> Option<&movegen::Placement> {
+        match self {
+            Guide::Drill(drill) => drill.target.as_ref(),
+            Guide::Opener(practice) => practice.target(),
+            Guide::Puzzle(_) | Guide::Versus(_) => None,
+        }
+    }
+
+    /// whether the game goes on after the player's ended, while the opponent still plays
+    fn is_running(&self, g: &gamestate::GameState) -> bool {
+        match self {
+            Guide::Versus(versus) => versus.is_running(g),
+            _ => false,
+        }
+    }
+
+    /// the game of the opponent, if there is one
+    fn opponent(&self) -> Option<&gamestate::GameState> {
+        match self {
+            Guide::Versus(versus) => Some(&versus.opponent),
+            _ => None,
+        }
+    }
+
+    /// how the practice is going so far
+    fn overlay(&self) -> Vec<String> {
+        let mut overlay = Vec::new();
+
+        match self {
+            Guide::Drill(drill) => {
+                let total = drill.total();
+                overlay.push(format!("{} of {} correct", total.correct, total.attempts));
+                match drill.last {
+                    Some(attempt) if attempt.is_correct() => overlay.push(format!("correct {:.2}s", attempt.frames as f64 / FPS as f64)),
+                    Some(attempt) if !attempt.placed => overlay.push("missed the target".to_string()),
+                    Some(attempt) => if let Some(check) = attempt.check {
+                        overlay.push(format!("{} inputs, {} needed", check.used, check.optimal));
+                    },
+                    None => {},
+                }
+            },
+            Guide::Opener(practice) => {
+                overlay.push(practice.opener.name.clone());
+                overlay.push(format!("step {} of {}", practice.step + 1, practice.opener.steps.len()));
+                overlay.push(format!("completed {}", practice.completed));
+                if practice.missed { overlay.push("wrong placement, starting over".to_string()) }
+            },
+            Guide::Puzzle(mission) => {
+                overlay.push(mission.puzzle.name.clone());
+                overlay.push(mission.puzzle.goal.to_string());
+                if let puzzle::Goal::Lines(goal) = mission.puzzle.goal {
+                    overlay.push(format!("{} of {} lines", mission.lines.min(goal), goal));
+                }
+            },
+            Guide::Versus(versus) => {
+                overlay.push(format!("incoming {}", versus.pending[0]));
+                overlay.push(format!("bot incoming {}", versus.pending[1]));
+            },
+        }
+
+        overlay
+    }
+}
+
+/// Prints the accuracy and average time of every drilled shape, orientation and column.
+fn print_drill(drill: &drill::Drill) {
+    let mut stats: Vec<_> = drill.stats.iter().collect();
+    stats.sort_by_key(|&(&(shape, orientation, column), _)| (shape.to_char(), orientation as u8, column));
+
+    println!("piece orientation column correct attempts seconds");
+    for (&(shape, orientation, column), stats) in stats {
+        println!("{} {} {} {} {} {:.2}",
+            shape.to_char(), orientation, column + 1, stats.correct, stats.attempts,
+            stats.frames as f64 / stats.attempts as f64 / FPS as f64);
+    }
+
+    let total = drill.total();
+    if total.attempts > 0 {
+        println!("{} of {} correct", total.correct, total.attempts);
+    }
+}
+
+/// Plays a replay twice without a window and compares the state hashes of every frame,
+/// and against a stored hash stream to catch engine changes, which is written if it does not exist yet.
+/// Returns whether everything matched.
+fn verify(path: &str, hashes_path: Option<&str>) -> bool {
+    let replay = match replay::Replay::load(path) {
+        Ok(replay) => replay,
+        Err(e) => { eprintln!("{}", e); return false },
+    };
+
+    let hashes = replay::hashes(&replay);
+    if let Some(tick) = replay::first_divergence(&hashes, &replay::hashes(&replay)) {
+        println!("nondeterministic, the runs diverge in frame {}", tick);
+        return false;
+    }
+    println!("deterministic over {} frames", hashes.len() - 1);
+
+    let hashes_path = match hashes_path {
+        Some(hashes_path) => hashes_path,
+        None => return true,
+    };
+
+    if !std::path::Path::new(hashes_path).exists() {
+        if let Err(e) = replay::save_hashes(hashes_path, &hashes) { eprintln!("{}", e); return false }
+        println!("wrote {}", hashes_path);
+        return true;
+    }
+
+    match replay::load_hashes(hashes_path) {
+        Ok(expected) => match replay::first_divergence(&expected, &hashes) {
+            Some(tick) => { println!("diverges from {} in frame {}", hashes_path, tick); false },
+            None => { println!("matches {}", hashes_path); true },
+        },
+        Err(e) => { eprintln!("{}", e); false },
+    }
+}
+
+/// Runs the game on player input, or the bot's if given, until it ends, returns whether the player quit.
+/// With `health` weights the board is evaluated every frame and shown next to it,
+/// with `finesse` every piece is checked for wasted inputs and the faults are shown,
+/// with a `guide` every piece gets a target outline, from a finesse drill or an opener, or the bot plays alongside.
+fn play(
+    renderer: &mut dyn rendering::Renderer,
+    g: &mut gamestate::GameState,
+    mut recording: Option<&mut replay::Replay>,
+    mut bot: Option<&mut bot::Bot>,
+    health: Option<&eval::Weights>,
+    mut finesse: Option<&mut finesse::Finesse>,
+    mut guide: Option<&mut Guide>,
+) -> bool {
+    let mut frame: usize = 0;
+
     let mut left = false;
     let mut right = false;
     let mut down = false;
 
-    while g.state == gamestate::State::Running {
-        if frame % (200 / millispf as usize) == 0 { g.step(); }
+    // placements can be taken back in zen, unless the game is recorded as undo is not part of a replay,
+    // and of the guides only opener practice keeps up with it
+    let mut history = match g.mode {
+        gamestate::Mode::Zen { .. } if recording.is_none() && bot.is_none() && matches!(guide.as_deref(), None | Some(Guide::Opener(_))) => Some(history::History::new()),
+        _ => None,
+    };
 
-        renderer.draw(&mut g);
+    while g.state == gamestate::State::Running || guide.as_deref().is_some_and(|guide| guide.is_running(g)) {
+        g.tick();
+
+        let mut overlay = Vec::new();
+        if let Some(weights) = health {
+            let features = eval::Features::from_grid(&g.grid);
+            overlay.push(format!("health {:.1}", weights.evaluate(&features)));
+            overlay.push(format!("holes {}", features.holes));
+            overlay.push(format!("height {}", features.max_height));
+            overlay.push(format!("bumps {}", features.bumpiness));
+        }
+        if let Some(finesse) = finesse.as_ref() {
+            overlay.push(format!("faults {}", finesse.faults));
+            if let Some(check) = finesse.last {
+                overlay.push(format!("{} {} inputs, {} needed", check.shape.to_char(), check.used, check.optimal));
+            }
+        }
+        if let Some(guide) = guide.as_ref() {
+            overlay.extend(guide.overlay());
+            renderer.set_target(guide.target().map(|target| (target.tetromino, target.pos)));
+            renderer.set_opponent(guide.opponent().cloned());
+        }
+        if health.is_some() || finesse.is_some() || guide.is_some() { renderer.set_overlay(overlay) }
+
+        renderer.draw(g);
+
+        let mut actions = Vec::new();
 
         let events = renderer.get_events();
-        if events.contains(&util::Event::Quit) { break }
+        if events.contains(&util::Event::Quit) { return true }
         if events.contains(&util::Event::KeyDown(util::Keycode::D)) { right = true }
         if events.contains(&util::Event::KeyDown(util::Keycode::A)) { left = true }
         if events.contains(&util::Event::KeyDown(util::Keycode::S)) { down = true }
-        if events.contains(&util::Event::KeyDown(util::Keycode::Space)) { g.rotate(util::RotDirection::Clockwise) }
-        if events.contains(&util::Event::KeyDown(util::Keycode::Shift)) { g.rotate(util::RotDirection::CounterClockwise) }
+        if events.contains(&util::Event::KeyDown(util::Keycode::Space)) { actions.push(util::Action::RotateClockwise) }
+        if events.contains(&util::Event::KeyDown(util::Keycode::Shift)) { actions.push(util::Action::RotateCounterClockwise) }
+        if events.contains(&util::Event::KeyDown(util::Keycode::C)) { actions.push(util::Action::Hold) }
+        if events.contains(&util::Event::KeyDown(util::Keycode::F)) { println!("{}", fumen::encode(&[fumen::Page::from_gamestate(g)])) }
+        if events.contains(&util::Event::KeyDown(util::Keycode::P)) { print_placements(g) }
         if events.contains(&util::Event::KeyUp(util::Keycode::D)) { right = false }
         if events.contains(&util::Event::KeyUp(util::Keycode::A)) { left = false }
         if events.contains(&util::Event::KeyUp(util::Keycode::S)) { down = false }
-        if events.contains(&util::Event::KeyDown(util::Keycode::W)) {
-            loop {
-                if let Err(_) = g.fall() { break; }
-            }
+        if events.contains(&util::Event::KeyDown(util::Keycode::W)) { actions.push(util::Action::HardDrop) }
+
+        if let Some(history) = history.as_mut() {
+            if events.contains(&util::Event::KeyDown(util::Keycode::Z)) { history.undo(g); }
+            if events.contains(&util::Event::KeyDown(util::Keycode::Y)) { history.redo(g); }
+        }
+
+        if frame % (100 / MILLISPF as usize) == 0 {
+            if right { actions.push(util::Action::MoveRight) }
+            if left { actions.push(util::Action::MoveLeft) }
         }
 
-        if frame % (100 / millispf as usize) == 0 {
-            if right { g.move_right() }
-            if left { g.move_left() }
+        if frame % (100 / MILLISPF as usize) == 0 {
+            if down { actions.push(util::Action::SoftDrop) }
         }
 
-        if frame % (100 / millispf as usize) == 0 {
-            if down { let _ = g.fall(); }
+        if let Some(bot) = bot.as_mut() { actions = bot.actions(g) }
+
+        for action in actions {
+            g.apply(action);
+            if let Some(recording) = recording.as_mut() { recording.record(g, action) }
         }
-        
-        std::thread::sleep(std::time::Duration::from_millis(millispf));
+
+        if let Some(history) = history.as_mut() { history.update(g) }
+        if let Some(finesse) = finesse.as_mut() { finesse.update(g) }
+        if let Some(guide) = guide.as_mut() { guide.update(g) }
+
+        std::thread::sleep(std::time::Duration::from_millis(MILLISPF));
         frame += 1;
     }
+
+    false
+}
+
+/// playback speeds the viewer steps through
+const SPEEDS: [f64; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+
+/// Plays a replay back until the player quits, or presses return once it has ended.
+///
+/// Space pauses, the arrow keys step a frame or change the speed, A and D step a piece,
+/// W and S jump ten seconds and the number keys jump to a tenth of the game.
+fn watch(renderer: &mut dyn rendering::Renderer, playback: &mut replay::Playback) {
+    let mut paused = false;
+    let mut speed = 2;
+    let mut progress = 0.0;
+
+    loop {
+        let events = renderer.get_events();
+        if events.contains(&util::Event::Quit) { return }
+        if events.contains(&util::Event::KeyDown(util::Keycode::Return)) && playback.finished() { return }
+
+        let ticks = playback.gamestate.ticks;
+        for event in events {
+            match event {
+                util::Event::KeyDown(util::Keycode::Space) => paused = !paused,
+                util::Event::KeyDown(util::Keycode::Right) => { paused = true; playback.seek(ticks + 1) },
+                util::Event::KeyDown(util::Keycode::Left) => { paused = true; playback.seek(ticks.saturating_sub(1)) },
+                util::Event::KeyDown(util::Keycode::D) => { paused = true; playback.next_piece() },
+                util::Event::KeyDown(util::Keycode::A) => { paused = true; playback.previous_piece() },
+                util::Event::KeyDown(util::Keycode::Up) => speed = (speed + 1).min(SPEEDS.len() - 1),
+                util::Event::KeyDown(util::Keycode::Down) => speed = speed.saturating_sub(1),
+                util::Event::KeyDown(util::Keycode::W) => playback.seek(ticks + 10 * FPS as usize),
+                util::Event::KeyDown(util::Keycode::S) => playback.seek(ticks.saturating_sub(10 * FPS as usize)),
+                util::Event::KeyDown(util::Keycode::Num(n)) => playback.seek(playback.replay.length * n as usize / 10),
+                _ => {},
+            }
+        }
+
+        if !paused {
+            progress += SPEEDS[speed];
+            while progress >= 1.0 {
+                playback.step();
+                progress -= 1.0;
+            }
+        }
+
+        renderer.set_overlay(vec![
+            if playback.finished() { "end".to_string() } else if paused { "paused".to_string() } else { format!("{}x", SPEEDS[speed]) },
+            format!("{}/{}s", playback.gamestate.ticks / FPS as usize, playback.replay.length / FPS as usize),
+            format!("piece {}", playback.gamestate.pieces),
+        ]);
+        renderer.draw(&mut playback.gamestate);
+
+        std::thread::sleep(std::time::Duration::from_millis(MILLISPF));
+    }
+}
+
+/// Keeps the final board on screen until the player leaves, returns whether the player quit.
+fn show_final_board(renderer: &mut dyn rendering::Renderer, g: &mut gamestate::GameState) -> bool {
+    loop {
+        renderer.draw(g);
+
+        let events = renderer.get_events();
+        if events.contains(&util::Event::Quit) { return true }
+        if events.contains(&util::Event::KeyDown(util::Keycode::Space)) { return false }
+
+        std::thread::sleep(std::time::Duration::from_millis(MILLISPF));
+    }
 }
@@ -0,0 +1,168 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::{gamestate::{Cell, GameState, SPAWN_POSITION}, tetromino::{Shape, Tetromino}, util::{Action, Orientation, Pos2d, RotDirection}};
+
+/// inputs tried from every position, in the order ties between equally short sequences are broken
+const INPUTS: [Action; 6] = [
+    Action::MoveLeft,
+    Action::MoveRight,
+    Action::RotateClockwise,
+    Action::RotateCounterClockwise,
+    Action::HardDrop,
+    Action::SoftDrop,
+];
+
+/// A resting position of a piece and the inputs that get it there from the spawn position.
+#[derive(Clone)]
+pub struct Placement {
+    pub tetromino: Tetromino,
+    pub pos: Pos2d,
+    /// the piece rests in place after these and locks once the lock delay ran out
+    pub inputs: Vec<Action>,
+}
+
+impl Placement {
+    /// grid cells the piece covers, sorted by row and column
+    pub fn cells(&self) -> [(usize, usize); 4] {
+        let mut cells = self.tetromino.get_tiles().map(|tile| ((tile.y as isize + self.pos.y) as usize, (tile.x as isize + self.pos.x) as usize));
+        cells.sort();
+        cells
+    }
+
+    /// Locks the piece into `grid` and removes full lines, returns how many were removed.
+    pub fn place(&self, grid: &mut [[Option<Cell>; 10]; 22]) -> usize {
+        for (y, x) in self.cells() {
+            grid[y][x] = Some(Cell { color: self.tetromino.color, locked_at: 0 });
+        }
+
+        let mut lines = 0;
+        for y in 0..22 {
+            if grid[y].iter().all(|cell| cell.is_some()) {
+                grid.copy_within(0..y, 1);
+                grid[0] = [None; 10];
+                lines += 1;
+            }
+        }
+        lines
+    }
+}
+
+/// Every placement `shape` can reach on the grid of `gamestate` when it spawns now.
+///
+/// Searches breadth first over moves, rotations with their kicks and drops, so tucks and spins
+/// are found and every placement comes with the fewest inputs. Placements covering the same
+/// cells in different orientations are listed once. Empty if the piece can not spawn.
+pub fn placements(gamestate: &GameState, shape: Shape) -> Vec<Placement> {
+    placements_from(gamestate, Tetromino::new(shape, Orientation::North), SPAWN_POSITION)
+}
+
+/// Like `placements`, but for a piece that already moved to `pos`, the inputs start from there.
+pub fn placements_from(gamestate: &GameState, tetro: Tetromino, pos: Pos2d) -> Vec<Placement> {
+    if !gamestate.fit_test(tetro, pos) { return Vec::new() }
+
+    let mut visited = HashSet::new();
+    let mut covered = HashSet::new();
+    let mut queue = VecDeque::new();
+    let mut placements = Vec::new();
+
+    visited.insert(get_key(tetro, pos));
+    queue.push_back((tetro, pos, Vec::new()));
+
+    while let Some((tetro, pos, inputs)) = queue.pop_front() {
+        if !gamestate.fit_test(tetro, Pos2d { x: pos.x, y: pos.y + 1 }) {
+            let placement = Placement { tetromino: tetro, pos, inputs: inputs.clone() };
+            if covered.insert(placement.cells()) { placements.push(placement) }
+        }
+
+        for input in INPUTS {
+            let next = match get_input_result(gamestate, tetro, pos, input) {
+                Some(next) => next,
+                None => continue,
+            };
+
+            if visited.insert(get_key(next.0, next.1)) {
+                let mut next_inputs = inputs.clone();
+                next_inputs.push(input);
+                queue.push_back((next.0, next.1, next_inputs));
+            }
+        }
+    }
+
+    placements
+}
+
+/// where an input moves the piece, `None` if it can not move
+fn get_input_result(gamestate: &GameState, tetro: Tetromino, pos: Pos2d, input: Action) -> Option<(Tetromino, Pos2d)> {
+    let shifted = |x, y| {
+        let pos = Pos2d { x: pos.x + x, y: pos.y + y };
+        if gamestate.fit_test(tetro, pos) { Some((tetro, pos)) } else { None }
+    };
+
+    match input {
+        Action::MoveLeft => shifted(-1, 0),
+        Action::MoveRight => shifted(1, 0),
+        Action::RotateClockwise => gamestate.rotated(tetro, pos, RotDirection::Clockwise),
+        Action::RotateCounterClockwise => gamestate.rotated(tetro, pos, RotDirection::CounterClockwise),
+        Action::SoftDrop => shifted(0, 1),
+        Action::HardDrop => {
+            let mut y = 0;
+            while gamestate.fit_test(tetro, Pos2d { x: pos.x, y: pos.y + y + 1 }) { y += 1 }
+            if y > 0 { Some((tetro, Pos2d { x: pos.x, y: pos.y + y })) } else { None }
+        },
+        Action::Hold => None,
+    }
+}
+
+fn get_key(tetro: Tetromino, pos: Pos2d) -> (u8, isize, isize) {
+    (tetro.orientation as u8, pos.x, pos.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{board::Board, gamestate::{Mode, Visibility}};
+
+    fn empty() -> GameState {
+        GameState::new(Mode::Zen { gravity: 0 }, Visibility::Normal)
+    }
+
+    /// where the inputs of `placement` leave the piece when applied to the game
+    fn play(gamestate: &GameState, placement: &Placement) -> [(usize, usize); 4] {
+        let mut gamestate = gamestate.clone();
+        gamestate.current = Some((Tetromino::new(placement.tetromino.shape, Orientation::North), SPAWN_POSITION));
+        for &input in placement.inputs.iter() { gamestate.apply(input) }
+
+        let (tetromino, pos) = gamestate.current.unwrap();
+        Placement { tetromino, pos, inputs: Vec::new() }.cells()
+    }
+
+    #[test]
+    fn every_column_and_rotation_on_an_empty_field() {
+        let gamestate = empty();
+
+        for (shape, count) in [(Shape::O, 9), (Shape::I, 17), (Shape::T, 34), (Shape::S, 17), (Shape::L, 34)] {
+            let placements = placements(&gamestate, shape);
+            assert_eq!(placements.len(), count, "{}", shape.to_char());
+
+            for placement in placements.iter() {
+                assert!(placement.cells().iter().any(|&(y, _)| y == 21), "{} floats", shape.to_char());
+                assert_eq!(play(&gamestate, placement), placement.cells());
+            }
+        }
+    }
+
+    #[test]
+    fn finds_spins_into_overhangs() {
+        let board = Board::parse(".Z........\nZZSS..J.LL\nZSS...JOOL\nIIII.JJOOL\n").unwrap();
+        let mut gamestate = empty();
+        board.apply(&mut gamestate);
+
+        // the T-spin double slot, the S above its left edge keeps a T from dropping in
+        let slot = placements(&gamestate, Shape::T).into_iter()
+            .find(|placement| placement.cells() == [(20, 3), (20, 4), (20, 5), (21, 4)])
+            .expect("no placement into the slot");
+
+        assert!(matches!(slot.inputs.last(), Some(Action::RotateClockwise | Action::RotateCounterClockwise)));
+        assert_eq!(play(&gamestate, &slot), slot.cells());
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{board, gamestate::{GameState, Mode, Visibility}, movegen::{self, Placement}, randomizer::Sequence, tetromino::Shape};
+
+/// A setup for the start of a game: the pieces in the order they are dealt and where each goes.
+///
+/// The text format has a `name` line, an optional `queue` line and up to 22 rows of 10 cells
+/// aligned to the bottom of the grid. `.` is empty and `a`, `b`, `c`, ... mark the cells of the
+/// first, second, third placement. Without a queue the pieces come in the order they are placed,
+/// otherwise hold is needed wherever the two differ. Lines starting with `#` are comments.
+/// Placements may not clear lines, so an opener ends before its first spin or clear.
+#[derive(Clone)]
+pub struct Opener {
+    pub name: String,
+    pub queue: Vec<Shape>,
+    /// placements in the order they are made, each reachable on the grid left by the ones before
+    pub steps: Vec<Placement>,
+}
+
+impl Opener {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut name = String::new();
+        let mut queue = None;
+        let mut rows = Vec::new();
+
+        for line in text.lines().map(|line| line.trim()) {
+            if line.is_empty() || line.starts_with('#') { continue }
+
+            if let Some(text) = line.strip_prefix("name") {
+                name = text.trim().to_string();
+                continue;
+            }
+            if let Some(text) = line.strip_prefix("queue") {
+                queue = Some(board::parse_queue(text.trim())?);
+                continue;
+            }
+
+            if let Some(c) = line.chars().find(|&c| c != '.' && !c.is_ascii_lowercase()) {
+                return Err(format!("invalid cell '{}'", c));
+            }
+            if line.len() != 10 { return Err(format!("row '{}' is not 10 cells wide", line)) }
+            rows.push(line);
+        }
+
+        if rows.len() > 22 { return Err(format!("{} rows do not fit into the grid", rows.len())) }
+
+        // cells of every step, row by row so they come out sorted like `Placement::cells`
+        let mut cells: Vec<Vec<(usize, usize)>> = Vec::new();
+        for (y, row) in (22 - rows.len()..22).zip(rows) {
+            for (x, c) in row.chars().enumerate().filter(|&(_, c)| c != '.') {
+                let step = (c as u8 - b'a') as usize;
+                if step >= cells.len() { cells.resize(step + 1, Vec::new()) }
+                cells[step].push((y, x));
+            }
+        }
+
+        let mut gamestate = GameState::new(Mode::Zen { gravity: 0 }, Visibility::Normal);
+        let mut steps = Vec::new();
+
+        for (step, cells) in cells.iter().enumerate() {
+            let letter = (b'a' + step as u8) as char;
+
+            let placement = [Shape::O, Shape::I, Shape::L, Shape::J, Shape::S, Shape::Z, Shape::T].into_iter()
+                .flat_map(|shape| movegen::placements(&gamestate, shape))
+                .find(|placement| placement.cells()[..] == cells[..])
+                .ok_or(format!("step '{}' is not a piece that can be placed there", letter))?;
+
+            if placement.place(&mut gamestate.grid) > 0 { return Err(format!("step '{}' clears lines", letter)) }
+            steps.push(placement);
+        }
+
+        if steps.is_empty() { return Err("no placements".to_string()) }
+
+        Ok(Self {
+            name,
+            queue: queue.unwrap_or_else(|| steps.iter().map(|step| step.tetromino.shape).collect()),
+            steps,
+        })
+    }
+}
+
+/// Guided practice of an opener, every placement is checked against the next step.
+///
+/// A wrong placement starts the opener over, so does finishing it. Placements can be undone
+/// like in zen, also back to before a wrong one.
+pub struct Practice {
+    pub opener: Opener,
+    /// index of the next step to place
+    pub step: usize,
+    pub completed: usize,
+    pub mistakes: usize,
+    /// whether the last placement did not match its step
+    pub missed: bool,
+    /// pieces already checked
+    pieces: usize,
+}
+
+impl Practice {
+    pub fn new(opener: Opener) -> Self {
+        Self { opener, step: 0, completed: 0, mistakes: 0, missed: false, pieces: 0 }
+    }
+
+    /// Clears the field and deals the opener's queue next, over and over until the opener is started again.
+    pub fn start(&mut self, gamestate: &mut GameState) {
+        gamestate.grid = [[None; 10]; 22];
+        gamestate.sequence = Some(Sequence::new(self.opener.queue.clone(), true));
+        gamestate.next.clear();
+        gamestate.hold = None;
+        gamestate.hold_used = false;
+        self.step = 0;
+        self.pieces = gamestate.pieces;
+    }
+
+    /// the placement to make next
+    pub fn target(&self) -> Option<&Placement> {
+        self.opener.steps.get(self.step)
+    }
+
+    /// Checks a newly locked piece, to be called once per frame after the game advanced.
+    ///
+    /// A game that jumped to another piece count had placements undone or redone, practice
+    /// picks up at the first step that is not on the field.
+    pub fn update(&mut self, gamestate: &mut GameState) {
+        if gamestate.pieces == self.pieces { return }
+
+        let locked = gamestate.pieces == self.pieces + 1 && gamestate.current.is_none();
+        self.pieces = gamestate.pieces;
+        if !locked {
+            self.step = self.opener.steps.iter()
+                .take_while(|step| step.cells().iter().all(|&(y, x)| gamestate.grid[y][x].is_some()))
+                .count();
+            self.missed = false;
+            return;
+        }
+
+        let placed = match (gamestate.last_lock.as_ref(), self.target()) {
+            (Some(lock), Some(target)) => Placement { tetromino: lock.tetromino, pos: lock.pos, inputs: Vec::new() }.cells() == target.cells(),
+            _ => false,
+        };
+
+        self.missed = !placed;
+        if !placed {
+            self.mistakes += 1;
+            self.start(gamestate);
+            return;
+        }
+
+        self.step += 1;
+        if self.step == self.opener.steps.len() {
+            self.completed += 1;
+            self.start(gamestate);
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::HashSet;
+
+use crate::{fumen::{self, Page}, gamestate::{Cell, GameState}, movegen::{self, Placement}, tetromino::Shape, util::Action};
+
+/// rows a perfect clear may use at most, counted from the floor
+const MAX_HEIGHT: usize = 4;
+
+/// Searches placements of the current piece, hold and the known queue that clear the whole grid,
+/// the queue continues with the pieces a sequence deals after the preview.
+///
+/// The grid may hold at most `MAX_HEIGHT` rows and at most `max_pieces` pieces are placed.
+/// A solution lists its placements in order, pieces taken out of hold have inputs starting with `Action::Hold`.
+/// Gaps in the stack whose size is not a multiple of four are given up on, which loses the rare
+/// solutions where a line clear joins two such gaps.
+pub fn solve(gamestate: &GameState, max_pieces: usize, max_solutions: usize) -> Vec<Vec<Placement>> {
+    let filled = gamestate.grid.iter().flatten().filter(|cell| cell.is_some()).count();
+    let height = gamestate.grid.iter().rev().take_while(|line| line.iter().any(|cell| cell.is_some())).count();
+    if gamestate.grid[..22 - height].iter().flatten().any(|cell| cell.is_some()) || height > MAX_HEIGHT { return Vec::new() }
+
+    let mut queue: Vec<Shape> = gamestate.current.map(|(tetro, _)| tetro.shape).into_iter()
+        .chain(gamestate.next.iter().rev().copied())
+        .collect();
+
+    // a sequence is known ahead, as far as the pieces and a hold need it
+    if let Some(mut sequence) = gamestate.sequence.clone() {
+        while queue.len() <= max_pieces {
+            let bag = sequence.next_bag();
+            if bag.is_empty() { break }
+            queue.extend(bag.into_iter().rev());
+        }
+    }
+    let available = queue.len() + gamestate.hold.is_some() as usize;
+
+    let mut search = Search {
+        queue,
+        hold_used: gamestate.hold_used,
+        max_solutions,
+        solutions: Vec::new(),
+        failed: HashSet::new(),
+    };
+
+    for height in height.max(1)..=MAX_HEIGHT {
+        let empty = 10 * height - filled;
+        if empty % 4 != 0 || empty / 4 > max_pieces.min(available) { continue }
+
+        let mut start = gamestate.clone();
+        start.current = None;
+        search.failed.clear();
+        search.run(&start, height, empty / 4, 0, gamestate.hold, &mut Vec::new());
+
+        if search.solutions.len() >= max_solutions { break }
+    }
+
+    search.solutions
+}
+
+/// A fumen with a page for each placement of `solution`, starting from the grid of `gamestate`.
+pub fn to_fumen(gamestate: &GameState, solution: &[Placement]) -> String {
+    let mut grid = gamestate.grid;
+    let mut pages = Vec::new();
+
+    for placement in solution {
+        pages.push(Page {
+            grid: grid.map(|line| line.map(|cell| cell.map(|cell| cell.color))),
+            piece: Some((placement.tetromino, placement.pos)),
+            comment: format!("PC in {}", solution.len()),
+            lock: true,
+        });
+        placement.place(&mut grid);
+    }
+
+    fumen::encode(&pages)
+}
+
+struct Search {
+    queue: Vec<Shape>,
+    /// whether hold is blocked for the first piece
+    hold_used: bool,
+    max_solutions: usize,
+    solutions: Vec<Vec<Placement>>,
+    /// positions known to have no solution, as the bits of the rows in use, rows left, queue index and hold
+    failed: HashSet<(u64, usize, usize, Option<Shape>)>,
+}
+
+impl Search {
+    /// Places `pieces` more pieces within the bottom `rows` rows, returns whether a solution was found.
+    fn run(&mut self, gamestate: &GameState, rows: usize, pieces: usize, index: usize, hold: Option<Shape>, path: &mut Vec<Placement>) -> bool {
+        if pieces == 0 {
+            if rows == 0 { self.solutions.push(path.clone()) }
+            return rows == 0;
+        }
+        if self.solutions.len() >= self.max_solutions { return true }
+
+        let key = (get_bits(&gamestate.grid, rows), rows, index, hold);
+        if self.failed.contains(&key) { return false }
+
+        // the next piece, or the held one with the next going into hold
+        let mut options = Vec::new();
+        if let Some(&shape) = self.queue.get(index) {
+            options.push((shape, index + 1, hold, false));
+        }
+        if !(self.hold_used && path.is_empty()) {
+            match hold {
+                Some(held) => if let Some(&shape) = self.queue.get(index) {
+                    if held != shape { options.push((held, index + 1, Some(shape), true)) }
+                },
+                None => if let (Some(&shape), Some(&next)) = (self.queue.get(index), self.queue.get(index + 1)) {
+                    options.push((next, index + 2, Some(shape), true))
+                },
+            }
+        }
+
+        let mut solved = false;
+        for (shape, next_index, next_hold, held) in options {
+            for mut placement in movegen::placements(gamestate, shape) {
+                if placement.cells().iter().any(|&(y, _)| y < 22 - rows) { continue }
+
+                let mut next = gamestate.clone();
+                let lines = placement.place(&mut next.grid);
+                if !has_fillable_gaps(&next.grid, rows - lines) { continue }
+
+                if held { placement.inputs.insert(0, Action::Hold) }
+                path.push(placement);
+                solved |= self.run(&next, rows - lines, pieces - 1, next_index, next_hold, path);
+                path.pop();
+
+                if self.solutions.len() >= self.max_solutions { return true }
+            }
+        }
+
+        if !solved { self.failed.insert(key); }
+        solved
+    }
+}
+
+/// the cells of the bottom `rows` rows as bits
+fn get_bits(grid: &[[Option<Cell>; 10]; 22], rows: usize) -> u64 {
+    grid[22 - rows..].iter().flatten().fold(0, |bits, cell| bits << 1 | cell.is_some() as u64)
+}
+
+/// Whether every connected gap in the bottom `rows` rows can be filled with whole pieces.
+fn has_fillable_gaps(grid: &[[Option<Cell>; 10]; 22], rows: usize) -> bool {
+    let mut seen = [[false; 10]; 22];
+
+    for y in 22 - rows..22 {
+        for x in 0..10 {
+            if grid[y][x].is_some() || seen[y][x] { continue }
+
+            let mut size = 0;
+            let mut stack = vec![(y, x)];
+            seen[y][x] = true;
+
+            while let Some((y, x)) = stack.pop() {
+                size += 1;
+                let neighbours = [(y.wrapping_sub(1), x), (y + 1, x), (y, x.wrapping_sub(1)), (y, x + 1)];
+                for (ny, nx) in neighbours {
+                    if ny < 22 - rows || ny >= 22 || nx >= 10 || grid[ny][nx].is_some() || seen[ny][nx] { continue }
+                    seen[ny][nx] = true;
+                    stack.push((ny, nx));
+                }
+            }
+
+            if size % 4 != 0 { return false }
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{board::Board, gamestate::{Mode, Visibility}, randomizer::Sequence};
+
+    /// whether the placements, in order, leave nothing on the grid of `gamestate`
+    fn clears(gamestate: &GameState, solution: &[Placement]) -> bool {
+        let mut grid = gamestate.grid;
+        for placement in solution { placement.place(&mut grid); }
+        grid.iter().flatten().all(|cell| cell.is_none())
+    }
+
+    #[test]
+    fn solves_from_the_queue() {
+        let board = Board::parse("queue TIOL\nLLLJJJ....\nLSOOZJ....\nSSOOZZ....\nSIIIIZ....\n").unwrap();
+        let mut gamestate = GameState::new(Mode::Zen { gravity: 0 }, Visibility::Normal);
+        board.apply(&mut gamestate);
+
+        let solutions = solve(&gamestate, 4, 3);
+        assert!(!solutions.is_empty());
+        for solution in solutions.iter() {
+            assert_eq!(solution.len(), 4);
+            assert!(clears(&gamestate, solution));
+        }
+    }
+
+    #[test]
+    fn solves_from_a_sequence() {
+        let gamestate = GameState::with_sequence(Mode::Zen { gravity: 0 }, Visibility::Normal, Sequence::parse("OOOOO").unwrap());
+
+        let solutions = solve(&gamestate, 5, 1);
+        assert_eq!(solutions.len(), 1);
+        assert!(clears(&gamestate, &solutions[0]));
+    }
+
+    #[test]
+    fn gives_up_without_enough_pieces() {
+        let gamestate = GameState::with_sequence(Mode::Zen { gravity: 0 }, Visibility::Normal, Sequence::parse("OOOO").unwrap());
+        assert!(solve(&gamestate, 10, 1).is_empty());
+
+        let board = Board::parse("queue OOOOOOOOOO\nG.........\n").unwrap();
+        let mut gamestate = GameState::new(Mode::Zen { gravity: 0 }, Visibility::Normal);
+        board.apply(&mut gamestate);
+        assert!(solve(&gamestate, 10, 1).is_empty());
+    }
+}
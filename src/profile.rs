@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{gamestate::{GameState, Mode, Visibility}, stats::Stats};
+
+/// bumped whenever a change to `Profile` breaks old profile files
+const PROFILE_VERSION: u64 = 1;
+/// where the profiles are kept, one file per player
+pub const PROFILE_DIR: &str = "./profiles";
+/// games a trend compares, the latest ones against as many before them
+const TREND_GAMES: usize = 5;
+
+/// A finished game as kept in a player's history.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Game {
+    pub mode: Mode,
+    pub visibility: Visibility,
+    /// when the game ended, in seconds since the unix epoch
+    pub date: u64,
+    pub score: usize,
+    pub lines: usize,
+    /// number of frames played
+    pub frames: usize,
+    pub stats: Stats,
+    /// randomizer seed, the same seed deals the same pieces
+    pub seed: u64,
+}
+
+impl Game {
+    /// The game as it ended just now.
+    pub fn from_gamestate(gamestate: &GameState) -> Self {
+        Self {
+            mode: gamestate.mode,
+            visibility: gamestate.visibility,
+            date: now(),
+            score: gamestate.score,
+            lines: gamestate.stats.lines(),
+            frames: gamestate.ticks,
+            stats: gamestate.stats,
+            seed: gamestate.randomizer.seed,
+        }
+    }
+
+    /// the day the game ended
+    pub fn day(&self) -> String {
+        day(self.date)
+    }
+}
+
+/// How a player is doing in one mode.
+pub struct Summary {
+    pub mode: Mode,
+    pub games: usize,
+    /// the game with the highest score
+    pub best: Game,
+    pub most_lines: usize,
+    /// best pieces per second of a game
+    pub best_pps: f64,
+    /// average score of the latest games, and of as many before them if there were that many
+    pub recent: (f64, Option<f64>),
+}
+
+/// A player's finished games, stored as versioned json in `PROFILE_DIR`.
+pub struct Profile {
+    pub name: String,
+    /// oldest first
+    pub games: Vec<Game>,
+}
+
+impl Profile {
+    pub fn new(name: &str) -> Self {
+        Self { name: name.to_string(), games: Vec::new() }
+    }
+
+    /// The file the profile is stored in.
+    ///
+    /// Names are limited to letters, digits, `-` and `_` so a profile can't be read or written outside `PROFILE_DIR`.
+    pub fn path(name: &str) -> Result<String, String> {
+        let plain = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !plain { return Err(format!("invalid profile name '{}'", name)) }
+        Ok(format!("{}/{}.json", PROFILE_DIR, name))
+    }
+
+    /// Reads a player's profile, a player without one yet starts out empty.
+    pub fn load(name: &str) -> Result<Self, String> {
+        let path = Self::path(name)?;
+        if !std::path::Path::new(&path).exists() { return Ok(Self::new(name)) }
+
+        let text = std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut profile: serde_json::Value = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+
+        match profile["version"].as_u64() {
+            Some(PROFILE_VERSION) => {},
+            Some(version) => return Err(format!("{}: unsupported profile version {}", path, version)),
+            None => return Err(format!("{}: not a profile", path)),
+        }
+
+        let games = serde_json::from_value(profile["games"].take()).map_err(|e| format!("{}: {}", path, e))?;
+        Ok(Self { name: name.to_string(), games })
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path(&self.name)?;
+        std::fs::create_dir_all(PROFILE_DIR).map_err(|e| format!("{}: {}", PROFILE_DIR, e))?;
+
+        let profile = serde_json::json!({ "version": PROFILE_VERSION, "games": self.games });
+        std::fs::write(&path, profile.to_string()).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Personal bests and the recent trend of every mode played, in the order the modes were first played.
+    pub fn summaries(&self) -> Vec<Summary> {
+        let mut modes: Vec<Mode> = Vec::new();
+        for game in &self.games {
+            if !modes.contains(&game.mode) { modes.push(game.mode) }
+        }
+
+        modes.into_iter().map(|mode| {
+            let games: Vec<&Game> = self.games.iter().filter(|game| game.mode == mode).collect();
+            let average = |games: &[&Game]| games.iter().map(|game| game.score as f64).sum::<f64>() / games.len() as f64;
+
+            let latest = games.len().saturating_sub(TREND_GAMES);
+            let before = &games[latest.saturating_sub(TREND_GAMES)..latest];
+
+            Summary {
+                mode,
+                games: games.len(),
+                best: (*games.iter().max_by_key(|game| game.score).unwrap()).clone(),
+                most_lines: games.iter().map(|game| game.lines).max().unwrap(),
+                best_pps: games.iter().map(|game| game.stats.pps()).fold(0.0, f64::max),
+                recent: (average(&games[latest..]), (before.len() == TREND_GAMES).then(|| average(before))),
+            }
+        }).collect()
+    }
+}
+
+/// seconds since the unix epoch
+pub fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+/// the day of a time in seconds since the unix epoch as year-month-day, in UTC
+pub fn day(date: u64) -> String {
+    // days to a civil date, after Howard Hinnant's `civil_from_days`
+    let days = (date / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+    format!("{}-{:02}-{:02}", year, month, day)
+}
@@ -0,0 +1,222 @@
+use crate::{board::Board, gamestate::{GameState, Mode, State, Visibility}, movegen::Placement, randomizer::Sequence, tetromino::Color};
+
+/// What a puzzle asks for before its queue runs out.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Goal {
+    /// at least this many lines in total
+    Lines(usize),
+    TspinDouble,
+    PerfectClear,
+    /// no garbage tiles left on the field
+    ClearGarbage,
+}
+
+impl Goal {
+    /// `lines <n>`, `tsd`, `pc` or `garbage`
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut words = text.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("lines"), Some(lines)) => lines.parse().map(Goal::Lines).map_err(|_| format!("invalid number of lines '{}'", lines)),
+            (Some("tsd"), None) => Ok(Goal::TspinDouble),
+            (Some("pc"), None) => Ok(Goal::PerfectClear),
+            (Some("garbage"), None) => Ok(Goal::ClearGarbage),
+            _ => Err(format!("unknown goal '{}'", text)),
+        }
+    }
+}
+
+impl std::fmt::Display for Goal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Goal::Lines(lines) => write!(f, "clear {} lines", lines),
+            Goal::TspinDouble => write!(f, "T-spin double"),
+            Goal::PerfectClear => write!(f, "perfect clear"),
+            Goal::ClearGarbage => write!(f, "clear all garbage"),
+        }
+    }
+}
+
+/// A starting position, the only pieces there are to play and what to achieve with them.
+///
+/// The text format is that of `Board` with a `name` and a `goal` line added.
+#[derive(Clone)]
+pub struct Puzzle {
+    pub name: String,
+    pub goal: Goal,
+    pub board: Board,
+}
+
+impl Puzzle {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut name = String::new();
+        let mut goal = None;
+        let mut rest = String::new();
+
+        for line in text.lines() {
+            if let Some(text) = line.trim().strip_prefix("name") {
+                name = text.trim().to_string();
+            } else if let Some(text) = line.trim().strip_prefix("goal") {
+                goal = Some(Goal::parse(text.trim())?);
+            } else {
+                rest.push_str(line);
+                rest.push('\n');
+            }
+        }
+
+        let board = Board::parse(&rest)?;
+        if board.queue.is_empty() { return Err("no queue".to_string()) }
+
+        Ok(Self { name, goal: goal.ok_or("no goal")?, board })
+    }
+
+    /// A game on the puzzle's board that ends once its queue ran out.
+    pub fn start(&self) -> GameState {
+        let mut gamestate = GameState::with_sequence(Mode::Zen { gravity: 0 }, Visibility::Normal, Sequence::new(Vec::new(), false));
+        self.board.apply(&mut gamestate);
+        gamestate
+    }
+}
+
+/// Playing a puzzle, every locked piece is checked against the goal.
+///
+/// The game is won as soon as the goal is reached and lost when the queue runs out before or the stack tops out.
+#[derive(Clone)]
+pub struct Mission {
+    pub puzzle: Puzzle,
+    /// lines cleared so far
+    pub lines: usize,
+    /// `Some(true)` once solved, `Some(false)` once failed
+    pub result: Option<bool>,
+    /// pieces already checked
+    pieces: usize,
+}
+
+impl Mission {
+    pub fn new(puzzle: Puzzle) -> Self {
+        Self { puzzle, lines: 0, result: None, pieces: 0 }
+    }
+
+    /// Checks a newly locked piece and ends the game on a result, to be called once per frame after the game advanced.
+    pub fn update(&mut self, gamestate: &mut GameState) {
+        if self.result.is_some() { return }
+
+        let new = gamestate.pieces > self.pieces;
+        self.pieces = gamestate.pieces;
+
+        if let Some(lock) = gamestate.last_lock.as_ref().filter(|_| new) {
+            // the field after the lock, zen would clear it when topping out
+            let mut grid = lock.grid;
+            let lines = Placement { tetromino: lock.tetromino, pos: lock.pos, inputs: Vec::new() }.place(&mut grid);
+            self.lines += lines;
+
+            let reached = match self.puzzle.goal {
+                Goal::Lines(goal) => self.lines >= goal,
+                Goal::TspinDouble => lock.tspin && lines == 2,
+                Goal::PerfectClear => grid.iter().flatten().all(|cell| cell.is_none()),
+                Goal::ClearGarbage => grid.iter().flatten().all(|cell| !cell.is_some_and(|cell| matches!(cell.color, Color::Gray))),
+            };
+
+            if reached {
+                self.result = Some(true);
+            } else if grid[..2].iter().flatten().any(|cell| cell.is_some()) {
+                self.result = Some(false);
+            }
+        }
+
+        if self.result.is_none() && gamestate.state != State::Running { self.result = Some(false) }
+
+        match self.result {
+            Some(true) => gamestate.state = State::Won,
+            Some(false) => gamestate.state = State::Lost,
+            None => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::{movegen, pc, tetromino::Shape, util::Action};
+
+    /// positions already searched without a solution, as the filled cells, hold, pieces and lines so far
+    type Failed = HashSet<(Vec<bool>, Option<Shape>, usize, usize)>;
+
+    /// rows above the starting stack a solution may use, shipped puzzles are solved low
+    const MARGIN: usize = 2;
+
+    /// positions searched at most before a puzzle counts as unsolvable, so a broken one fails instead of searching forever
+    const MAX_FAILED: usize = 2000;
+
+    /// Plays every placement of the current piece and of the one hold brings until the mission is solved,
+    /// keeping the pieces below row `top`.
+    fn solve(gamestate: &GameState, mission: &Mission, top: usize, failed: &mut Failed) -> bool {
+        let key = (gamestate.grid.iter().flatten().map(|cell| cell.is_some()).collect(), gamestate.hold, gamestate.pieces, mission.lines);
+        if failed.len() >= MAX_FAILED || failed.contains(&key) { return false }
+
+        for hold in [false, true] {
+            let mut start = gamestate.clone();
+            if hold {
+                start.apply(Action::Hold);
+                if !start.hold_used { continue }
+            }
+
+            let (tetro, pos) = match start.current {
+                Some(current) => current,
+                None => continue,
+            };
+
+            for placement in movegen::placements_from(&start, tetro, pos) {
+                if placement.cells().iter().any(|&(y, _)| y < top) { continue }
+
+                let mut g = start.clone();
+                let mut mission = mission.clone();
+                for &input in placement.inputs.iter() { g.apply(input) }
+                g.lock();
+                mission.update(&mut g);
+
+                while g.state == State::Running && g.current.is_none() {
+                    g.tick();
+                    mission.update(&mut g);
+                }
+
+                match mission.result {
+                    Some(true) => return true,
+                    Some(false) => {},
+                    None => if solve(&g, &mission, top, failed) { return true },
+                }
+            }
+        }
+
+        failed.insert(key);
+        false
+    }
+
+    #[test]
+    fn shipped_puzzles_are_solvable() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/res/puzzles");
+        let mut paths: Vec<_> = std::fs::read_dir(dir).unwrap().map(|entry| entry.unwrap().path()).collect();
+        paths.sort();
+        assert!(!paths.is_empty());
+
+        for path in paths {
+            let puzzle = Puzzle::load(path.to_str().unwrap()).unwrap();
+            let mut gamestate = puzzle.start();
+            gamestate.tick();
+            let stack = gamestate.grid.iter().position(|line| line.iter().any(|cell| cell.is_some())).unwrap_or(22);
+            let top = stack.saturating_sub(MARGIN);
+
+            let solved = match puzzle.goal {
+                Goal::PerfectClear => !pc::solve(&gamestate, puzzle.board.queue.len(), 1).is_empty(),
+                _ => solve(&gamestate, &Mission::new(puzzle.clone()), top, &mut Failed::new()),
+            };
+            assert!(solved, "{} can not be solved", puzzle.name);
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::tetromino::Shape;
+
+/// Deterministic source of the piece order and garbage holes.
+///
+/// Every draw derives its own generator from the seed and the number of previous draws,
+/// so the whole state is two numbers and a game can be reproduced from its seed.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Randomizer {
+    pub seed: u64,
+    pub draws: u64,
+}
+
+impl Randomizer {
+    pub fn new(seed: u64) -> Self {
+        Self { seed, draws: 0 }
+    }
+
+    pub fn from_entropy() -> Self {
+        Self::new(rand::thread_rng().gen())
+    }
+
+    fn rng(&mut self) -> StdRng {
+        let rng = StdRng::seed_from_u64(self.seed ^ self.draws.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        self.draws += 1;
+        rng
+    }
+
+    /// a shuffled bag of all seven pieces
+    pub fn next_bag(&mut self) -> Vec<Shape> {
+        let mut bag = vec![Shape::O, Shape::I, Shape::L, Shape::J, Shape::S, Shape::Z, Shape::T];
+        bag.shuffle(&mut self.rng());
+        bag
+    }
+
+    pub fn garbage_hole(&mut self) -> usize {
+        self.rng().gen_range(0..10)
+    }
+}
+
+/// A fixed order of pieces dealt instead of shuffled bags, once or over and over.
+///
+/// The text format lists the pieces, a trailing `*` repeats them. Whitespace is ignored
+/// and lines starting with `#` are comments.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Sequence {
+    pub pieces: Vec<Shape>,
+    pub repeat: bool,
+    /// whether a sequence that does not repeat was dealt already
+    dealt: bool,
+}
+
+impl Sequence {
+    pub fn new(pieces: Vec<Shape>, repeat: bool) -> Self {
+        Self { pieces, repeat, dealt: false }
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text: String = text.lines()
+            .filter(|line| !line.trim().starts_with('#'))
+            .flat_map(|line| line.chars())
+            .filter(|c| !c.is_whitespace())
+            .collect();
+
+        let (text, repeat) = match text.strip_suffix('*') {
+            Some(text) => (text, true),
+            None => (text.as_str(), false),
+        };
+
+        let pieces = text.chars()
+            .map(|c| Shape::from_char(c).ok_or(format!("invalid piece '{}'", c)))
+            .collect::<Result<Vec<Shape>, String>>()?;
+
+        if pieces.is_empty() { return Err("no pieces".to_string()) }
+        Ok(Self::new(pieces, repeat))
+    }
+
+    /// the next pieces like `Randomizer::next_bag`, empty once a sequence that does not repeat ran out
+    pub fn next_bag(&mut self) -> Vec<Shape> {
+        if self.dealt { return Vec::new() }
+        self.dealt = !self.repeat;
+        self.pieces.iter().rev().copied().collect()
+    }
+
+    /// whether there is nothing left to deal
+    pub fn is_finished(&self) -> bool {
+        self.dealt
+    }
+}
+
+impl std::fmt::Display for Sequence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.pieces.iter().map(|shape| shape.to_char()).collect::<String>())?;
+        if self.repeat { write!(f, "*")? }
+        Ok(())
+    }
+}
@@ -1,10 +1,18 @@
 use sdl2::ttf::Font;
 
-use crate::tetromino::Color;
+use tetris::tetromino::Color;
 
 pub trait Renderer {
-    fn draw(&mut self, gamestate: &mut crate::gamestate::GameState);
-    fn get_events(&mut self) -> Vec<crate::util::Event>;
+    fn draw(&mut self, gamestate: &mut tetris::gamestate::GameState);
+    fn get_events(&mut self) -> Vec<tetris::util::Event>;
+    /// lines of text shown on top of the game until replaced
+    fn set_overlay(&mut self, lines: Vec<String>);
+    /// a placement outlined on the field until replaced
+    fn set_target(&mut self, target: Option<(tetris::tetromino::Tetromino, tetris::util::Pos2d)>);
+    /// how often each grid cell was covered, from 0 to 1, shaded on the field until replaced
+    fn set_heatmap(&mut self, heatmap: Option<[[f32; 10]; 22]>);
+    /// a second game shown small next to the field until replaced
+    fn set_opponent(&mut self, opponent: Option<tetris::gamestate::GameState>);
 }
 
 use lazy_static::lazy_static;
@@ -17,6 +25,11 @@ pub struct SdlRenderer<'a> {
     event_pump: sdl2::EventPump,
     canvas: sdl2::render::WindowCanvas,
     font: sdl2::ttf::Font<'a, 'a>,
+    small_font: sdl2::ttf::Font<'a, 'a>,
+    overlay: Vec<String>,
+    target: Option<(tetris::tetromino::Tetromino, tetris::util::Pos2d)>,
+    heatmap: Option<[[f32; 10]; 22]>,
+    opponent: Option<tetris::gamestate::GameState>,
 }
 
 impl<'a> SdlRenderer<'a> {
@@ -42,11 +55,12 @@ impl<'a> SdlRenderer<'a> {
         let ttf_context = &TTF_CONTEXT;
 
         let font: Font<'a, 'a> = ttf_context.load_font("./res/VT323-Regular.ttf", 64).unwrap();
+        let small_font: Font<'a, 'a> = ttf_context.load_font("./res/VT323-Regular.ttf", 24).unwrap();
 
-        Self { event_pump, canvas, font }
+        Self { event_pump, canvas, font, small_font, overlay: Vec::new(), target: None, heatmap: None, opponent: None }
     }
 
-    fn get_draw_transforms(&self) -> (crate::util::Pos2d, f32) {
+    fn get_draw_transforms(&self) -> (tetris::util::Pos2d, f32) {
         let max_x_tilesize = self.canvas.output_size().unwrap().0 as f32 / 10.0;
         let max_y_tilesize = self.canvas.output_size().unwrap().1 as f32 / 20.0;
 
@@ -55,7 +69,20 @@ impl<'a> SdlRenderer<'a> {
         let x = ((self.canvas.output_size().unwrap().0 as f32 - 10.0 * tilesize) / 2.0) as isize;
         let y = ((self.canvas.output_size().unwrap().1 as f32 - 20.0 * tilesize) / 2.0) as isize;
 
-        (crate::util::Pos2d{ x, y }, tilesize)
+        (tetris::util::Pos2d{ x, y }, tilesize)
+    }
+
+    /// maps a point in window coordinates to the grid cell under it
+    fn get_cell(&self, x: i32, y: i32) -> Option<tetris::util::UPos2d> {
+        let (pos, tilesize) = self.get_draw_transforms();
+        let scale = self.canvas.output_size().unwrap().0 as f32 / self.canvas.window().size().0 as f32;
+
+        let x = (x as f32 * scale - pos.x as f32) / tilesize;
+        let y = (y as f32 * scale - pos.y as f32) / tilesize;
+
+        if x < 0.0 || y < 0.0 || x >= 10.0 || y >= 20.0 { return None }
+
+        Some(tetris::util::UPos2d{ x: x as usize, y: y as usize + 2 })
     }
 
     fn draw_grid(&mut self) {
@@ -77,23 +104,33 @@ impl<'a> SdlRenderer<'a> {
         }
     }
 
-    fn draw_tiles(&mut self, gamestate: &mut crate::gamestate::GameState) {
+    fn draw_tiles(&mut self, gamestate: &mut tetris::gamestate::GameState) {
         let (pos, tilesize) = self.get_draw_transforms();
 
         for (i, row) in gamestate.grid.into_iter().rev().take(20).rev().enumerate() {
             for (j, col) in row.into_iter().enumerate() {
-
-                let (r, g, b) = match col {
-                    Some(Color::Red) => (255, 0, 0),
-                    Some(Color::Blue) => (0, 0, 255),
-                    Some(Color::Green) => (0, 255, 0),
-                    Some(Color::Orange) => (235, 69, 17),
-                    Some(Color::Purple) => (56, 2, 59),
-                    Some(Color::Teal) => (34, 124, 157),
-                    Some(Color::Yellow) => (255, 255, 0),
+                let cell = match col {
+                    Some(cell) => cell,
                     None => { continue; },
                 };
 
+                let brightness = match gamestate.visibility {
+                    _ if gamestate.state != tetris::gamestate::State::Running => 1.0,
+                    tetris::gamestate::Visibility::Normal => 1.0,
+                    tetris::gamestate::Visibility::Invisible => 0.0,
+                    tetris::gamestate::Visibility::Fading => {
+                        1.0 - (gamestate.ticks - cell.locked_at) as f32 / tetris::gamestate::FADE_FRAMES as f32
+                    },
+                };
+                if brightness <= 0.0 { continue; }
+
+                let (r, g, b) = rgb(cell.color);
+                let (r, g, b) = (
+                    (r as f32 * brightness) as u8,
+                    (g as f32 * brightness) as u8,
+                    (b as f32 * brightness) as u8,
+                );
+
                 let (x, y) = (
                     (j as f32 * tilesize) as i32,
                     (i as f32 * tilesize) as i32,
@@ -113,22 +150,14 @@ impl<'a> SdlRenderer<'a> {
         }
     }
 
-    fn draw_tetro(&mut self, gamestate: &mut crate::gamestate::GameState) {
+    fn draw_tetro(&mut self, gamestate: &mut tetris::gamestate::GameState) {
         if gamestate.current.is_none() { return; }
         let (tetro, pos) = gamestate.current.unwrap();
 
         let (draw_pos, tilesize) = self.get_draw_transforms();
 
         for tile in tetro.get_tiles().into_iter() {
-            let (r, g, b) = match tetro.color {
-                Color::Red => (255, 0, 0),
-                Color::Blue => (0, 0, 255),
-                Color::Green => (0, 255, 0),
-                Color::Orange => (235, 69, 17),
-                Color::Purple => (56, 2, 59),
-                Color::Teal => (34, 124, 157),
-                Color::Yellow => (255, 255, 0),
-            };
+            let (r, g, b) = rgb(tetro.color);
 
             let (x, y) = (
                 ((pos.x + tile.x as isize) as f32 * tilesize) as i32,
@@ -148,8 +177,138 @@ impl<'a> SdlRenderer<'a> {
         }
     }
 
-    fn draw_score(&mut self, gamestate: &mut crate::gamestate::GameState) {
-        let text_surface = self.font.render(&gamestate.score.to_string())
+    /// the outline of the target placement in the color of its piece
+    fn draw_target(&mut self) {
+        let (tetro, pos) = match self.target {
+            Some(target) => target,
+            None => { return; },
+        };
+
+        let (draw_pos, tilesize) = self.get_draw_transforms();
+
+        let (r, g, b) = rgb(tetro.color);
+        self.canvas.set_draw_color(sdl2::pixels::Color::RGB(r, g, b));
+
+        for tile in tetro.get_tiles().into_iter() {
+            let (x, y) = (
+                ((pos.x + tile.x as isize) as f32 * tilesize) as i32,
+                ((pos.y + tile.y as isize - 2) as f32 * tilesize) as i32,
+            );
+
+            let (w, h) = (
+                (((pos.x + tile.x as isize + 1) as f32 * tilesize) as i32 - x) as u32,
+                (((pos.y + tile.y as isize - 1) as f32 * tilesize) as i32 - y) as u32,
+            );
+
+            self.canvas
+                .draw_rect(sdl2::rect::Rect::new(draw_pos.x as i32 + x + 1, draw_pos.y as i32 + y + 1, w - 2, h - 2))
+                .unwrap();
+        }
+    }
+
+    /// the visible cells of the heatmap, from black to red
+    fn draw_heatmap(&mut self) {
+        let heatmap = match self.heatmap {
+            Some(heatmap) => heatmap,
+            None => { return; },
+        };
+
+        let (pos, tilesize) = self.get_draw_transforms();
+
+        for (i, row) in heatmap.iter().skip(2).enumerate() {
+            for (j, &heat) in row.iter().enumerate() {
+                if heat <= 0.0 { continue; }
+
+                let (x, y) = ((j as f32 * tilesize) as i32, (i as f32 * tilesize) as i32);
+                let (w, h) = (
+                    (((j + 1) as f32 * tilesize) as i32 - x) as u32,
+                    (((i + 1) as f32 * tilesize) as i32 - y) as u32,
+                );
+
+                self.canvas.set_draw_color(sdl2::pixels::Color::RGB((heat * 255.0) as u8, (heat * 64.0) as u8, 0));
+                self.canvas.fill_rect(sdl2::rect::Rect::new(pos.x as i32 + x, pos.y as i32 + y, w, h)).unwrap();
+            }
+        }
+    }
+
+    /// the opponent's field and piece at a quarter of the tile size in the top right corner
+    fn draw_opponent(&mut self) {
+        let opponent = match self.opponent.as_ref() {
+            Some(opponent) => opponent,
+            None => { return; },
+        };
+
+        let mut grid = opponent.grid.map(|line| line.map(|cell| cell.map(|cell| cell.color)));
+        if let Some((tetro, pos)) = opponent.current {
+            for tile in tetro.get_tiles() {
+                grid[(tile.y as isize + pos.y) as usize][(tile.x as isize + pos.x) as usize] = Some(tetro.color);
+            }
+        }
+
+        let (_, tilesize) = self.get_draw_transforms();
+        let tilesize = tilesize / 4.0;
+        let canvas_width = self.canvas.output_size().unwrap().0 as i32;
+        let (left, top) = (canvas_width - 4 - (10.0 * tilesize) as i32, 4);
+
+        self.canvas.set_draw_color(sdl2::pixels::Color::RGB(25, 25, 25));
+        self.canvas.draw_rect(sdl2::rect::Rect::new(left - 1, top - 1, (10.0 * tilesize) as u32 + 2, (20.0 * tilesize) as u32 + 2)).unwrap();
+
+        for (i, row) in grid.iter().skip(2).enumerate() {
+            for (j, cell) in row.iter().enumerate() {
+                let color = match cell {
+                    Some(color) => *color,
+                    None => { continue; },
+                };
+
+                let (r, g, b) = rgb(color);
+
+                let (x, y) = ((j as f32 * tilesize) as i32, (i as f32 * tilesize) as i32);
+                let (w, h) = (
+                    (((j + 1) as f32 * tilesize) as i32 - x) as u32,
+                    (((i + 1) as f32 * tilesize) as i32 - y) as u32,
+                );
+
+                self.canvas.set_draw_color(sdl2::pixels::Color::RGB(r, g, b));
+                self.canvas.fill_rect(sdl2::rect::Rect::new(left + x, top + y, w, h)).unwrap();
+            }
+        }
+    }
+
+    /// the held piece at half the tile size in the top left corner
+    fn draw_hold(&mut self, gamestate: &mut tetris::gamestate::GameState) {
+        let shape = match gamestate.hold {
+            Some(shape) => shape,
+            None => { return; },
+        };
+        let tetro = tetris::tetromino::Tetromino::new(shape, tetris::util::Orientation::North);
+
+        let (_, tilesize) = self.get_draw_transforms();
+        let tilesize = tilesize / 2.0;
+
+        let (r, g, b) = rgb(tetro.color);
+        let color = if gamestate.hold_used { sdl2::pixels::Color::RGB(128, 128, 128) } else { sdl2::pixels::Color::RGB(r, g, b) };
+        self.canvas.set_draw_color(color);
+
+        for tile in tetro.get_tiles().into_iter() {
+            let (x, y) = ((tile.x as f32 * tilesize) as i32, (tile.y as f32 * tilesize) as i32);
+            let (w, h) = (
+                (((tile.x + 1) as f32 * tilesize) as i32 - x) as u32,
+                (((tile.y + 1) as f32 * tilesize) as i32 - y) as u32,
+            );
+
+            self.canvas
+                .fill_rect(sdl2::rect::Rect::new(4 + x, 4 + y, w, h))
+                .unwrap();
+        }
+    }
+
+    fn draw_score(&mut self, gamestate: &mut tetris::gamestate::GameState) {
+        let text = match gamestate.mode {
+            tetris::gamestate::Mode::Master => format!("{} LV{}", gamestate.score, gamestate.level),
+            _ => gamestate.score.to_string(),
+        };
+
+        let text_surface = self.font.render(&text)
             .blended(sdl2::pixels::Color::RGBA(255, 255, 255, 255))
             .map_err(|e| e.to_string()).unwrap();
 
@@ -164,44 +323,172 @@ impl<'a> SdlRenderer<'a> {
 
         let _ = self.canvas.copy(&text_texture, None, Some(sdl2::rect::Rect::new((canvas_width as i32 - width as i32) / 2, 0, width, height)));
     }
+
+    fn draw_overlay(&mut self) {
+        let texture_creator = self.canvas.texture_creator();
+
+        for (i, line) in self.overlay.iter().enumerate() {
+            if line.is_empty() { continue; }
+
+            let text_surface = self.small_font.render(line)
+                .blended(sdl2::pixels::Color::RGBA(255, 255, 255, 255))
+                .map_err(|e| e.to_string()).unwrap();
+
+            let text_texture = texture_creator
+                .create_texture_from_surface(&text_surface)
+                .map_err(|e| e.to_string()).unwrap();
+
+            let sdl2::render::TextureQuery { width, height, .. } = text_texture.query();
+
+            let _ = self.canvas.copy(&text_texture, None, Some(sdl2::rect::Rect::new(4, 64 + i as i32 * height as i32, width, height)));
+        }
+    }
+
+    /// the game's statistics, right aligned along the bottom of the window
+    fn draw_stats(&mut self, gamestate: &mut tetris::gamestate::GameState) {
+        let texture_creator = self.canvas.texture_creator();
+        let (canvas_width, canvas_height) = self.canvas.output_size().unwrap();
+        let lines = gamestate.stats.summary();
+
+        for (i, line) in lines.iter().enumerate() {
+            let text_surface = self.small_font.render(line)
+                .blended(sdl2::pixels::Color::RGBA(160, 160, 160, 255))
+                .map_err(|e| e.to_string()).unwrap();
+
+            let text_texture = texture_creator
+                .create_texture_from_surface(&text_surface)
+                .map_err(|e| e.to_string()).unwrap();
+
+            let sdl2::render::TextureQuery { width, height, .. } = text_texture.query();
+            let y = canvas_height as i32 - (lines.len() - i) as i32 * height as i32 - 4;
+
+            let _ = self.canvas.copy(&text_texture, None, Some(sdl2::rect::Rect::new(canvas_width as i32 - width as i32 - 4, y, width, height)));
+        }
+    }
 }
 
 impl Renderer for SdlRenderer<'_> {
-    fn draw(&mut self, gamestate: &mut crate::gamestate::GameState) {
+    fn draw(&mut self, gamestate: &mut tetris::gamestate::GameState) {
 
         self.canvas.set_draw_color(sdl2::pixels::Color::RGB(0, 0, 0));
         self.canvas.clear();
+        self.draw_heatmap();
         self.draw_tiles(gamestate);
+        self.draw_target();
         self.draw_tetro(gamestate);
+        self.draw_hold(gamestate);
+        self.draw_opponent();
         self.draw_grid();
         self.draw_score(gamestate);
+        if gamestate.ticks > 0 { self.draw_stats(gamestate) }
+        self.draw_overlay();
 
         self.canvas.present();
     }
 
-    fn get_events(&mut self) -> Vec<crate::util::Event> {
+    fn get_events(&mut self) -> Vec<tetris::util::Event> {
         let mut events = Vec::new();
 
-        for event in self.event_pump.poll_iter() {
+        for event in self.event_pump.poll_iter().collect::<Vec<sdl2::event::Event>>() {
             match event {
                 sdl2::event::Event::Quit { .. }
                 | sdl2::event::Event::KeyDown {
                     keycode: Some(sdl2::keyboard::Keycode::Escape),
                     ..
-                } => { events.push(crate::util::Event::Quit) },
-                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::W), repeat: false, .. } => { events.push(crate::util::Event::KeyDown(crate::util::Keycode::W)) },
-                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::A), repeat: false, .. } => { events.push(crate::util::Event::KeyDown(crate::util::Keycode::A)) },
-                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::S), repeat: false, .. } => { events.push(crate::util::Event::KeyDown(crate::util::Keycode::S)) },
-                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::D), repeat: false, .. } => { events.push(crate::util::Event::KeyDown(crate::util::Keycode::D)) },
-                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Space), repeat: false, .. } => { events.push(crate::util::Event::KeyDown(crate::util::Keycode::Space)) },
-                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::LShift), repeat: false, .. } => { events.push(crate::util::Event::KeyDown(crate::util::Keycode::Shift)) },
-                sdl2::event::Event::KeyUp { keycode: Some(sdl2::keyboard::Keycode::W), repeat: false, .. } => { events.push(crate::util::Event::KeyUp(crate::util::Keycode::W)) },
-                sdl2::event::Event::KeyUp { keycode: Some(sdl2::keyboard::Keycode::A), repeat: false, .. } => { events.push(crate::util::Event::KeyUp(crate::util::Keycode::A)) },
-                sdl2::event::Event::KeyUp { keycode: Some(sdl2::keyboard::Keycode::S), repeat: false, .. } => { events.push(crate::util::Event::KeyUp(crate::util::Keycode::S)) },
-                sdl2::event::Event::KeyUp { keycode: Some(sdl2::keyboard::Keycode::D), repeat: false, .. } => { events.push(crate::util::Event::KeyUp(crate::util::Keycode::D)) },
+                } => { events.push(tetris::util::Event::Quit) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::W), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::W)) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::A), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::A)) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::S), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::S)) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::D), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::D)) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Space), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::Space)) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::LShift), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::Shift)) },
+                sdl2::event::Event::KeyUp { keycode: Some(sdl2::keyboard::Keycode::W), repeat: false, .. } => { events.push(tetris::util::Event::KeyUp(tetris::util::Keycode::W)) },
+                sdl2::event::Event::KeyUp { keycode: Some(sdl2::keyboard::Keycode::A), repeat: false, .. } => { events.push(tetris::util::Event::KeyUp(tetris::util::Keycode::A)) },
+                sdl2::event::Event::KeyUp { keycode: Some(sdl2::keyboard::Keycode::S), repeat: false, .. } => { events.push(tetris::util::Event::KeyUp(tetris::util::Keycode::S)) },
+                sdl2::event::Event::KeyUp { keycode: Some(sdl2::keyboard::Keycode::D), repeat: false, .. } => { events.push(tetris::util::Event::KeyUp(tetris::util::Keycode::D)) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::I), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::I)) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::O), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::O)) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::T), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::T)) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Z), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::Z)) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::L), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::L)) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::J), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::J)) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Tab), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::Tab)) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Backspace), .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::Backspace)) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Return), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::Return)) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::C), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::C)) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::F), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::F)) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Y), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::Y)) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::P), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::P)) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Left), .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::Left)) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Right), .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::Right)) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Up), .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::Up)) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Down), .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::Down)) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Num0), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::Num(0))) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Num1), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::Num(1))) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Num2), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::Num(2))) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Num3), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::Num(3))) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Num4), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::Num(4))) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Num5), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::Num(5))) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Num6), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::Num(6))) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Num7), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::Num(7))) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Num8), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::Num(8))) },
+                sdl2::event::Event::KeyDown { keycode: Some(sdl2::keyboard::Keycode::Num9), repeat: false, .. } => { events.push(tetris::util::Event::KeyDown(tetris::util::Keycode::Num(9))) },
+                sdl2::event::Event::TextInput { text, .. } => { events.extend(text.chars().map(tetris::util::Event::Text)) },
+                sdl2::event::Event::MouseButtonDown { mouse_btn, x, y, .. } => {
+                    let button = match mouse_btn {
+                        sdl2::mouse::MouseButton::Left => Some(tetris::util::MouseButton::Left),
+                        sdl2::mouse::MouseButton::Right => Some(tetris::util::MouseButton::Right),
+                        _ => None,
+                    };
+                    if let (Some(button), Some(cell)) = (button, self.get_cell(x, y)) {
+                        events.push(tetris::util::Event::Mouse(button, cell))
+                    }
+                },
+                sdl2::event::Event::MouseMotion { mousestate, x, y, .. } => {
+                    let button = if mousestate.left() {
+                        Some(tetris::util::MouseButton::Left)
+                    } else if mousestate.right() {
+                        Some(tetris::util::MouseButton::Right)
+                    } else {
+                        None
+                    };
+                    if let (Some(button), Some(cell)) = (button, self.get_cell(x, y)) {
+                        events.push(tetris::util::Event::Mouse(button, cell))
+                    }
+                },
                 _ => {}
             }
         };
         events
     }
+
+    fn set_overlay(&mut self, lines: Vec<String>) {
+        self.overlay = lines;
+    }
+
+    fn set_target(&mut self, target: Option<(tetris::tetromino::Tetromino, tetris::util::Pos2d)>) {
+        self.target = target;
+    }
+
+    fn set_heatmap(&mut self, heatmap: Option<[[f32; 10]; 22]>) {
+        self.heatmap = heatmap;
+    }
+
+    fn set_opponent(&mut self, opponent: Option<tetris::gamestate::GameState>) {
+        self.opponent = opponent;
+    }
+}
+
+/// the red, green and blue of a tile color
+fn rgb(color: Color) -> (u8, u8, u8) {
+    match color {
+        Color::Red => (255, 0, 0),
+        Color::Blue => (0, 0, 255),
+        Color::Green => (0, 255, 0),
+        Color::Orange => (235, 69, 17),
+        Color::Purple => (56, 2, 59),
+        Color::Teal => (34, 124, 157),
+        Color::Yellow => (255, 255, 0),
+        Color::Gray => (128, 128, 128),
+    }
 }
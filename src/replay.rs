@@ -0,0 +1,415 @@
+use crate::{fumen, gamestate::{GameState, Mode, State, Visibility}, randomizer::{Randomizer, Sequence}, util::Action};
+
+const MAGIC: &[u8; 4] = b"TRPL";
+const VERSION: u8 = 2;
+
+/// A recorded game: everything needed to re-drive the engine deterministically.
+///
+/// The file is binary: magic, version, mode, visibility, seed, the number of frames,
+/// the starting position as fumen, the piece sequence as text (empty without one, missing in version 1)
+/// and then every action as frame delta and action byte.
+#[derive(Clone)]
+pub struct Replay {
+    pub mode: Mode,
+    pub visibility: Visibility,
+    pub seed: u64,
+    /// starting position as fumen, so games from custom boards replay too
+    pub start: String,
+    /// pieces dealt instead of the randomizer's bags
+    pub sequence: Option<Sequence>,
+    /// actions with the frame they were applied in
+    pub actions: Vec<(usize, Action)>,
+    /// number of frames the game ran for
+    pub length: usize,
+}
+
+impl Replay {
+    /// Starts recording a game that is about to begin from `gamestate`.
+    pub fn new(gamestate: &GameState) -> Self {
+        Self {
+            mode: gamestate.mode,
+            visibility: gamestate.visibility,
+            seed: gamestate.randomizer.seed,
+            start: fumen::encode(&[fumen::Page::from_gamestate(gamestate)]),
+            sequence: gamestate.sequence.clone(),
+            actions: Vec::new(),
+            length: gamestate.ticks,
+        }
+    }
+
+    pub fn record(&mut self, gamestate: &GameState, action: Action) {
+        self.actions.push((gamestate.ticks, action));
+        self.length = gamestate.ticks;
+    }
+
+    pub fn finish(&mut self, gamestate: &GameState) {
+        self.length = gamestate.ticks;
+    }
+
+    /// the game state before the first frame, the start position was checked when the replay was read
+    pub fn start(&self) -> GameState {
+        let mut gamestate = GameState::new(self.mode, self.visibility);
+        gamestate.randomizer = Randomizer::new(self.seed);
+        gamestate.sequence = self.sequence.clone();
+
+        if let Some(page) = fumen::decode(&self.start).ok().and_then(|pages| pages.into_iter().next()) {
+            page.apply(&mut gamestate);
+        }
+
+        gamestate
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+        Self::from_bytes(&bytes).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_bytes()).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+
+        match self.mode {
+            Mode::Marathon => bytes.push(0),
+            Mode::Survival => bytes.push(1),
+            Mode::Master => bytes.push(2),
+            Mode::Zen { gravity } => {
+                bytes.push(3);
+                write_varint(&mut bytes, gravity);
+            },
+        }
+
+        bytes.push(match self.visibility {
+            Visibility::Normal => 0,
+            Visibility::Invisible => 1,
+            Visibility::Fading => 2,
+        });
+
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        write_varint(&mut bytes, self.length);
+        write_varint(&mut bytes, self.start.len());
+        bytes.extend_from_slice(self.start.as_bytes());
+
+        let sequence = self.sequence.as_ref().map(|sequence| sequence.to_string()).unwrap_or_default();
+        write_varint(&mut bytes, sequence.len());
+        bytes.extend_from_slice(sequence.as_bytes());
+
+        write_varint(&mut bytes, self.actions.len());
+        let mut last = 0;
+        for &(tick, action) in self.actions.iter() {
+            write_varint(&mut bytes, tick - last);
+            bytes.push(get_action_byte(action));
+            last = tick;
+        }
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader { bytes, index: 0 };
+
+        if reader.take(4)? != MAGIC { return Err("not a replay file".to_string()) }
+        let version = reader.byte()?;
+        if version == 0 || version > VERSION { return Err(format!("unsupported replay version {}", version)) }
+
+        let mode = match reader.byte()? {
+            0 => Mode::Marathon,
+            1 => Mode::Survival,
+            2 => Mode::Master,
+            3 => Mode::Zen { gravity: reader.varint()? },
+            mode => return Err(format!("unknown mode {}", mode)),
+        };
+
+        let visibility = match reader.byte()? {
+            0 => Visibility::Normal,
+            1 => Visibility::Invisible,
+            2 => Visibility::Fading,
+            visibility => return Err(format!("unknown visibility {}", visibility)),
+        };
+
+        let seed = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+        let length = reader.varint()?;
+        let start_length = reader.varint()?;
+        let start = String::from_utf8(reader.take(start_length)?.to_vec()).map_err(|e| e.to_string())?;
+        if fumen::decode(&start).map_err(|e| format!("start position: {}", e))?.is_empty() {
+            return Err("start position: no page".to_string());
+        }
+
+        let sequence = match version {
+            1 => None,
+            _ => {
+                let length = reader.varint()?;
+                let text = String::from_utf8(reader.take(length)?.to_vec()).map_err(|e| e.to_string())?;
+                if text.is_empty() { None } else { Some(Sequence::parse(&text)?) }
+            },
+        };
+
+        let count = reader.varint()?;
+        let mut actions = Vec::new();
+        let mut tick = 0;
+        for _ in 0..count {
+            tick = reader.varint()?.checked_add(tick).ok_or("action past the end of time")?;
+            let action = reader.byte()?;
+            actions.push((tick, get_byte_action(action).ok_or(format!("unknown action {}", action))?));
+        }
+
+        Ok(Self { mode, visibility, seed, start, sequence, actions, length })
+    }
+}
+
+/// frames between two snapshots kept for seeking backwards
+const SNAPSHOT_INTERVAL: usize = 150;
+
+/// Re-drives a `GameState` frame by frame from a replay and seeks to any frame of it.
+pub struct Playback {
+    pub replay: Replay,
+    pub gamestate: GameState,
+    /// index of the next action to apply
+    index: usize,
+    /// states every `SNAPSHOT_INTERVAL` frames with their action index, as far as the replay was played
+    snapshots: Vec<(GameState, usize)>,
+    /// frames in which pieces locked, as far as the replay was played
+    locks: Vec<usize>,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        let gamestate = replay.start();
+        let snapshots = vec![(gamestate.clone(), 0)];
+        Self { replay, gamestate, index: 0, snapshots, locks: Vec::new() }
+    }
+
+    /// Advances one frame and applies the actions recorded for it.
+    pub fn step(&mut self) {
+        if self.finished() { return }
+
+        self.gamestate.tick();
+
+        while let Some(&(tick, action)) = self.replay.actions.get(self.index) {
+            if tick > self.gamestate.ticks { break }
+            self.gamestate.apply(action);
+            self.index += 1;
+        }
+
+        if self.gamestate.ticks == self.snapshots.len() * SNAPSHOT_INTERVAL {
+            self.snapshots.push((self.gamestate.clone(), self.index));
+        }
+        if self.gamestate.pieces > self.locks.len() {
+            self.locks.push(self.gamestate.ticks);
+        }
+    }
+
+    pub fn finished(&self) -> bool {
+        self.gamestate.state != State::Running || self.gamestate.ticks >= self.replay.length
+    }
+
+    /// Jumps to frame `tick`, restoring the closest earlier snapshot and playing forward from there.
+    pub fn seek(&mut self, tick: usize) {
+        let tick = tick.min(self.replay.length);
+
+        // restore a snapshot when going back or when one lies between here and the target
+        let (snapshot, index) = self.snapshots.iter().rev().find(|(snapshot, _)| snapshot.ticks <= tick).unwrap();
+        if tick < self.gamestate.ticks || snapshot.ticks > self.gamestate.ticks {
+            self.gamestate = snapshot.clone();
+            self.index = *index;
+        }
+
+        while self.gamestate.ticks < tick && !self.finished() {
+            self.step();
+        }
+    }
+
+    pub fn next_piece(&mut self) {
+        let pieces = self.gamestate.pieces;
+        while self.gamestate.pieces == pieces && !self.finished() {
+            self.step();
+        }
+    }
+
+    /// Jumps back to the frame the previous piece locked in.
+    pub fn previous_piece(&mut self) {
+        let tick = self.locks.iter().rev().find(|&&tick| tick < self.gamestate.ticks).copied().unwrap_or(0);
+        self.seek(tick);
+    }
+}
+
+/// State hashes after every frame of the replay, the first one is of the starting position.
+pub fn hashes(replay: &Replay) -> Vec<u64> {
+    let mut playback = Playback::new(replay.clone());
+    let mut hashes = vec![playback.gamestate.hash()];
+
+    while !playback.finished() {
+        playback.step();
+        hashes.push(playback.gamestate.hash());
+    }
+
+    hashes
+}
+
+/// The first frame two hash streams differ in, a shorter stream differs where it ends.
+pub fn first_divergence(a: &[u64], b: &[u64]) -> Option<usize> {
+    match a.iter().zip(b).position(|(a, b)| a != b) {
+        Some(tick) => Some(tick),
+        None if a.len() != b.len() => Some(a.len().min(b.len())),
+        None => None,
+    }
+}
+
+/// Reads a hash stream written by `save_hashes`, one hex hash per line.
+pub fn load_hashes(path: &str) -> Result<Vec<u64>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| u64::from_str_radix(line.trim(), 16).map_err(|e| format!("{}:{}: {}", path, i + 1, e)))
+        .collect()
+}
+
+pub fn save_hashes(path: &str, hashes: &[u64]) -> Result<(), String> {
+    let text: String = hashes.iter().map(|hash| format!("{:016x}\n", hash)).collect();
+    std::fs::write(path, text).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn get_action_byte(action: Action) -> u8 {
+    match action {
+        Action::MoveLeft => 0,
+        Action::MoveRight => 1,
+        Action::RotateClockwise => 2,
+        Action::RotateCounterClockwise => 3,
+        Action::SoftDrop => 4,
+        Action::HardDrop => 5,
+        Action::Hold => 6,
+    }
+}
+
+fn get_byte_action(byte: u8) -> Option<Action> {
+    match byte {
+        0 => Some(Action::MoveLeft),
+        1 => Some(Action::MoveRight),
+        2 => Some(Action::RotateClockwise),
+        3 => Some(Action::RotateCounterClockwise),
+        4 => Some(Action::SoftDrop),
+        5 => Some(Action::HardDrop),
+        6 => Some(Action::Hold),
+        _ => None,
+    }
+}
+
+/// LEB128, seven bits per byte with the high bit marking that more follow
+fn write_varint(bytes: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        bytes.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    index: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let end = self.index.checked_add(count).ok_or("unexpected end of replay")?;
+        let bytes = self.bytes.get(self.index..end).ok_or("unexpected end of replay")?;
+        self.index = end;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn varint(&mut self) -> Result<usize, String> {
+        let mut value = 0;
+        let mut shift = 0;
+
+        loop {
+            let byte = self.byte()?;
+            if shift >= usize::BITS { return Err("varint too long".to_string()) }
+            value |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 { return Ok(value) }
+            shift += 7;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bot::Bot, eval::Weights, FPS};
+
+    /// A survival game played by the bot for `pieces` pieces, recorded the way the game loop records the player.
+    fn record(seed: u64, pieces: usize) -> (GameState, Replay) {
+        let mut gamestate = GameState::new(Mode::Survival, Visibility::Normal);
+        gamestate.randomizer = Randomizer::new(seed);
+        let mut replay = Replay::new(&gamestate);
+        let mut bot = Bot::new(Weights::default(), 0, FPS as f64);
+
+        while gamestate.state == State::Running && gamestate.pieces < pieces {
+            gamestate.tick();
+            for action in bot.actions(&gamestate) {
+                gamestate.apply(action);
+                replay.record(&gamestate, action);
+            }
+        }
+        replay.finish(&gamestate);
+
+        (gamestate, replay)
+    }
+
+    #[test]
+    fn round_trip_replays_the_game() {
+        let (gamestate, replay) = record(7, 30);
+        let replay = Replay::from_bytes(&replay.to_bytes()).unwrap();
+
+        let mut playback = Playback::new(replay.clone());
+        while !playback.finished() { playback.step() }
+
+        assert_eq!(playback.gamestate.hash(), gamestate.hash());
+        assert_eq!(playback.gamestate.pieces, 30);
+        assert_eq!(first_divergence(&hashes(&replay), &hashes(&replay)), None);
+    }
+
+    #[test]
+    fn seeking_back_and_forth_is_deterministic() {
+        let (gamestate, replay) = record(11, 30);
+        assert!(gamestate.ticks > SNAPSHOT_INTERVAL);
+        let mut playback = Playback::new(replay);
+
+        playback.seek(gamestate.ticks);
+        let end = playback.gamestate.hash();
+        playback.seek(SNAPSHOT_INTERVAL / 2);
+        playback.previous_piece();
+        playback.seek(gamestate.ticks);
+
+        assert_eq!(playback.gamestate.hash(), end);
+        assert_eq!(end, gamestate.hash());
+    }
+
+    #[test]
+    fn oversized_lengths_are_errors() {
+        let (_, replay) = record(3, 0);
+        assert!(replay.actions.is_empty());
+        let bytes = replay.to_bytes();
+
+        // right after magic, version, mode, visibility and seed follows the length, then the start position's length
+        let mut start = bytes[..15].to_vec();
+        write_varint(&mut start, replay.length);
+        write_varint(&mut start, usize::MAX);
+        assert!(Replay::from_bytes(&start).is_err());
+
+        // the count of no actions replaced by two whose frame deltas add up past the largest frame
+        let mut actions = bytes;
+        actions.pop();
+        write_varint(&mut actions, 2);
+        for _ in 0..2 {
+            write_varint(&mut actions, usize::MAX);
+            actions.push(get_action_byte(Action::MoveLeft));
+        }
+        assert!(Replay::from_bytes(&actions).is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{gamestate::{Mode, Visibility}, profile};
+
+/// bumped whenever a change to `Scores` breaks old score files
+const SCORES_VERSION: u64 = 1;
+/// where the high scores of all modes are kept
+pub const SCORES_PATH: &str = "./scores.json";
+/// entries kept per table
+pub const TABLE_SIZE: usize = 10;
+/// characters a name may have at most
+pub const NAME_LENGTH: usize = 12;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub name: String,
+    pub score: usize,
+    pub lines: usize,
+    /// when the game ended, in seconds since the unix epoch
+    pub date: u64,
+}
+
+impl Entry {
+    /// An entry for a game that ended just now.
+    pub fn new(name: &str, score: usize, lines: usize) -> Self {
+        Self { name: name.chars().take(NAME_LENGTH).collect(), score, lines, date: profile::now() }
+    }
+
+    pub fn day(&self) -> String {
+        profile::day(self.date)
+    }
+}
+
+/// The best entries of one mode and visibility, highest score first.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Table {
+    pub mode: Mode,
+    /// tables from before visibilities were told apart are the normal ones
+    #[serde(default)]
+    pub visibility: Visibility,
+    pub entries: Vec<Entry>,
+}
+
+/// High score tables of every mode and visibility played, stored as versioned json.
+#[derive(Default)]
+pub struct Scores {
+    pub tables: Vec<Table>,
+}
+
+impl Scores {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the tables, there are none yet without the file.
+    pub fn load(path: &str) -> Result<Self, String> {
+        if !std::path::Path::new(path).exists() { return Ok(Self::new()) }
+
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut scores: serde_json::Value = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+
+        match scores["version"].as_u64() {
+            Some(SCORES_VERSION) => {},
+            Some(version) => return Err(format!("{}: unsupported scores version {}", path, version)),
+            None => return Err(format!("{}: not a scores file", path)),
+        }
+
+        let mut tables: Vec<Table> = serde_json::from_value(scores["tables"].take()).map_err(|e| format!("{}: {}", path, e))?;
+
+        // the file may have been edited by hand
+        for table in tables.iter_mut() {
+            table.entries.sort_by_key(|entry| std::cmp::Reverse(entry.score));
+            table.entries.truncate(TABLE_SIZE);
+        }
+
+        Ok(Self { tables })
+    }
+
+    /// Reads the tables like `load`, but a file that can't be read starts them over.
+    ///
+    /// The file is moved aside to `<path>.bad` first so saving does not overwrite it,
+    /// the error is returned along to be reported.
+    pub fn load_or_recover(path: &str) -> (Self, Option<String>) {
+        match Self::load(path) {
+            Ok(scores) => (scores, None),
+            Err(e) => {
+                let backup = format!("{}.bad", path);
+                let e = match std::fs::rename(path, &backup) {
+                    Ok(()) => format!("{}, moved to {}", e, backup),
+                    Err(rename) => format!("{}, {}: {}", e, backup, rename),
+                };
+                (Self::new(), Some(e))
+            },
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let scores = serde_json::json!({ "version": SCORES_VERSION, "tables": self.tables });
+        std::fs::write(path, scores.to_string()).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// the entries of a mode and visibility, highest score first
+    pub fn table(&self, mode: Mode, visibility: Visibility) -> &[Entry] {
+        self.tables.iter().find(|table| table.mode == mode && table.visibility == visibility).map_or(&[], |table| &table.entries)
+    }
+
+    /// whether a score would make it into the table of the mode and visibility
+    pub fn qualifies(&self, mode: Mode, visibility: Visibility, score: usize) -> bool {
+        let entries = self.table(mode, visibility);
+        score > 0 && (entries.len() < TABLE_SIZE || entries.iter().any(|entry| score > entry.score))
+    }
+
+    /// Adds an entry below those with the same score, returns its rank from 0 if it made it into the table.
+    pub fn insert(&mut self, mode: Mode, visibility: Visibility, entry: Entry) -> Option<usize> {
+        if !self.qualifies(mode, visibility, entry.score) { return None }
+
+        let index = match self.tables.iter().position(|table| table.mode == mode && table.visibility == visibility) {
+            Some(index) => index,
+            None => {
+                self.tables.push(Table { mode, visibility, entries: Vec::new() });
+                self.tables.len() - 1
+            },
+        };
+
+        let entries = &mut self.tables[index].entries;
+        let rank = entries.iter().take_while(|other| other.score >= entry.score).count();
+        entries.insert(rank, entry);
+        entries.truncate(TABLE_SIZE);
+        Some(rank)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tables_keep_the_best_per_mode_and_visibility() {
+        let mut scores = Scores::new();
+        for score in 1..=TABLE_SIZE + 2 {
+            scores.insert(Mode::Marathon, Visibility::Normal, Entry::new("a", score * 100, 0));
+        }
+        assert_eq!(scores.insert(Mode::Marathon, Visibility::Normal, Entry::new("b", 50, 0)), None);
+        assert_eq!(scores.insert(Mode::Marathon, Visibility::Normal, Entry::new("b", 1200, 0)), Some(1));
+
+        let table = scores.table(Mode::Marathon, Visibility::Normal);
+        assert_eq!(table.len(), TABLE_SIZE);
+        assert_eq!(table[0].score, 1200);
+        assert_eq!(table[1].name, "b");
+
+        assert!(scores.table(Mode::Marathon, Visibility::Invisible).is_empty());
+        assert_eq!(scores.insert(Mode::Marathon, Visibility::Invisible, Entry::new("c", 50, 0)), Some(0));
+        assert_eq!(scores.table(Mode::Marathon, Visibility::Normal).len(), TABLE_SIZE);
+    }
+
+    #[test]
+    fn tables_without_visibility_are_normal() {
+        let path = std::env::temp_dir().join(format!("tetris-scores-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        std::fs::write(path, r#"{"version":1,"tables":[{"mode":"Marathon","entries":[{"name":"a","score":10,"lines":1,"date":0}]}]}"#).unwrap();
+
+        let scores = Scores::load(path);
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(scores.unwrap().table(Mode::Marathon, Visibility::Normal).len(), 1);
+    }
+}
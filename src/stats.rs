@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{finesse, gamestate::Lock, FPS};
+
+/// lines sent for 0 to 4 lines cleared at once
+const ATTACK: [usize; 5] = [0, 0, 1, 2, 4];
+/// lines sent for a T-spin clearing 0 to 3 lines
+const TSPIN_ATTACK: [usize; 4] = [0, 2, 4, 6];
+/// extra lines sent by the n-th clear in a row, the last entry holds for longer combos
+const COMBO_ATTACK: [usize; 12] = [0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5];
+const PERFECT_CLEAR_ATTACK: usize = 10;
+
+/// Running totals of a game, updated by the game itself as it goes.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Stats {
+    pub pieces: usize,
+    /// keys pressed for pieces, holding a direction to the wall or a soft drop counts as one press
+    pub keys: usize,
+    /// pieces placed with more inputs than the fewest that reach the same cells
+    #[serde(default)]
+    pub finesse_faults: usize,
+    /// clears by the number of lines, index 0 is unused
+    pub clears: [usize; 5],
+    /// T-spins by the number of lines they cleared, including none
+    pub tspins: [usize; 4],
+    pub perfect_clears: usize,
+    /// garbage lines the clears would send to an opponent
+    pub attack: usize,
+    /// clears in a row so far, 0 after a piece that cleared nothing
+    pub combo: usize,
+    pub max_combo: usize,
+    /// whether the last clear was a tetris or a T-spin, which makes the next one send an extra line
+    pub back_to_back: bool,
+    /// number of frames played
+    pub frames: usize,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts a locked piece, `perfect_clear` if its lines left the field empty.
+    pub fn record(&mut self, lock: &Lock, perfect_clear: bool) {
+        self.pieces += 1;
+        self.keys += finesse::count_keys(&lock.grid, lock.tetromino.shape, &lock.inputs);
+        if finesse::check(lock).is_some_and(|check| check.is_fault()) { self.finesse_faults += 1 }
+
+        if lock.tspin { self.tspins[lock.lines.min(3)] += 1 }
+
+        if lock.lines == 0 {
+            self.combo = 0;
+            return;
+        }
+
+        self.clears[lock.lines.min(4)] += 1;
+        self.combo += 1;
+        self.max_combo = self.max_combo.max(self.combo);
+
+        let difficult = lock.tspin || lock.lines >= 4;
+        let mut attack = if lock.tspin { TSPIN_ATTACK[lock.lines.min(3)] } else { ATTACK[lock.lines.min(4)] };
+        if difficult && self.back_to_back { attack += 1 }
+        attack += COMBO_ATTACK[(self.combo - 1).min(COMBO_ATTACK.len() - 1)];
+        if perfect_clear {
+            self.perfect_clears += 1;
+            attack += PERFECT_CLEAR_ATTACK;
+        }
+
+        self.attack += attack;
+        self.back_to_back = difficult;
+    }
+
+    /// total lines cleared
+    pub fn lines(&self) -> usize {
+        self.clears.iter().enumerate().map(|(lines, clears)| lines * clears).sum()
+    }
+
+    /// time played in seconds
+    pub fn seconds(&self) -> f64 {
+        self.frames as f64 / FPS as f64
+    }
+
+    /// pieces per second
+    pub fn pps(&self) -> f64 {
+        if self.frames == 0 { return 0.0 }
+        self.pieces as f64 / self.seconds()
+    }
+
+    /// attack per minute
+    pub fn apm(&self) -> f64 {
+        if self.frames == 0 { return 0.0 }
+        self.attack as f64 * 60.0 / self.seconds()
+    }
+
+    /// keys per piece
+    pub fn kpp(&self) -> f64 {
+        if self.pieces == 0 { return 0.0 }
+        self.keys as f64 / self.pieces as f64
+    }
+
+    /// One line per figure, as shown next to the field and printed after a game.
+    pub fn summary(&self) -> Vec<String> {
+        let frames = self.frames as u64;
+        vec![
+            format!("time {}:{:02}.{:02}", frames / FPS / 60, frames / FPS % 60, frames % FPS * 100 / FPS),
+            format!("pieces {}", self.pieces),
+            format!("pps {:.2}", self.pps()),
+            format!("kpp {:.2}", self.kpp()),
+            format!("finesse faults {}", self.finesse_faults),
+            format!("apm {:.1}", self.apm()),
+            format!("attack {}", self.attack),
+            format!("lines {}", self.lines()),
+            format!("singles {}", self.clears[1]),
+            format!("doubles {}", self.clears[2]),
+            format!("triples {}", self.clears[3]),
+            format!("tetrises {}", self.clears[4]),
+            format!("tspins {}/{}/{}/{}", self.tspins[0], self.tspins[1], self.tspins[2], self.tspins[3]),
+            format!("max combo {}", self.max_combo),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{gamestate::{GameState, Mode, Visibility}, util::Action};
+
+    /// the keys and finesse faults after placing one piece with `inputs`
+    fn place(inputs: &[Action]) -> (usize, usize) {
+        let mut g = GameState::new(Mode::Zen { gravity: 0 }, Visibility::Normal);
+        g.tick();
+        for &input in inputs { g.apply(input) }
+        g.lock();
+        (g.stats.keys, g.stats.finesse_faults)
+    }
+
+    #[test]
+    fn auto_repeat_counts_as_one_key() {
+        // held right past the wall, as the game repeats a held direction
+        let mut held = vec![Action::MoveRight; 6];
+        held.push(Action::HardDrop);
+        assert_eq!(place(&held), (2, 0));
+        // two taps that stop short of the wall
+        assert_eq!(place(&[Action::MoveRight, Action::MoveRight, Action::HardDrop]), (3, 0));
+        // held soft drop locking the piece
+        assert_eq!(place(&[Action::SoftDrop; 10]), (1, 0));
+    }
+
+    #[test]
+    fn counts_holds_and_finesse_faults() {
+        assert_eq!(place(&[Action::MoveLeft, Action::Hold, Action::HardDrop]), (3, 0));
+        assert_eq!(place(&[Action::MoveLeft, Action::MoveRight, Action::HardDrop]), (3, 1));
+    }
+}
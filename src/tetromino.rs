@@ -1,10 +1,11 @@
 use std::fmt::Display;
+use serde::{Deserialize, Serialize};
 use crate::util::{self, Pos2d, RotDirection};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Shape { O, I, L, J, S, Z, T }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub enum Color {
     Teal,
     Blue,
@@ -13,9 +14,55 @@ pub enum Color {
     Green,
     Purple,
     Red,
+    Gray,
 }
 
-#[derive(Clone, Copy)]
+impl Shape {
+    pub fn from_char(c: char) -> Option<Self> {
+        match c.to_ascii_uppercase() {
+            'O' => Some(Shape::O),
+            'I' => Some(Shape::I),
+            'L' => Some(Shape::L),
+            'J' => Some(Shape::J),
+            'S' => Some(Shape::S),
+            'Z' => Some(Shape::Z),
+            'T' => Some(Shape::T),
+            _ => None,
+        }
+    }
+
+    pub fn to_char(self) -> char {
+        match self {
+            Shape::O => 'O',
+            Shape::I => 'I',
+            Shape::L => 'L',
+            Shape::J => 'J',
+            Shape::S => 'S',
+            Shape::Z => 'Z',
+            Shape::T => 'T',
+        }
+    }
+
+    pub fn color(self) -> Color {
+        get_shape_color(self)
+    }
+
+    /// the shape a tile color belongs to, `None` for garbage
+    pub fn from_color(color: Color) -> Option<Self> {
+        match color {
+            Color::Teal => Some(Shape::I),
+            Color::Blue => Some(Shape::J),
+            Color::Orange => Some(Shape::L),
+            Color::Yellow => Some(Shape::O),
+            Color::Green => Some(Shape::S),
+            Color::Purple => Some(Shape::T),
+            Color::Red => Some(Shape::Z),
+            Color::Gray => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct Tetromino {
     pub shape: Shape,
     pub orientation: util::Orientation,
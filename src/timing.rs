@@ -0,0 +1,40 @@
+/// Frame based timings of the piece lifecycle. All durations are in frames of the 30 fps game loop.
+#[derive(Clone, Copy)]
+pub struct Timing {
+    /// rows fallen per frame in 1/256ths, 5120 is 20G
+    pub gravity: usize,
+    /// entry delay between locking a piece and spawning the next one
+    pub are: usize,
+    pub line_clear: usize,
+    /// frames a grounded piece may stay in place before it locks
+    pub lock_delay: usize,
+}
+
+/// roughly one row every 200ms, a grounded piece locks on the next row's worth of time
+pub const CLASSIC: Timing = Timing { gravity: 43, are: 0, line_clear: 0, lock_delay: 6 };
+
+pub const MASTER_MAX_LEVEL: usize = 999;
+
+/// (level, gravity) pairs, the gravity applies from its level up to the next entry
+const MASTER_GRAVITY: [(usize, usize); 30] = [
+    (0, 8), (30, 12), (35, 16), (40, 20), (50, 24), (60, 32), (70, 64), (80, 96), (90, 128), (100, 160),
+    (120, 192), (140, 224), (160, 256), (170, 288), (200, 8), (220, 64), (230, 128), (233, 192), (236, 256), (239, 320),
+    (243, 384), (247, 448), (251, 512), (300, 1024), (330, 1536), (360, 2048), (400, 2560), (420, 2048), (450, 1536), (500, 5120),
+];
+
+/// (section start level, are, line clear, lock delay)
+const MASTER_DELAYS: [(usize, usize, usize, usize); 6] = [
+    (0, 12, 20, 15),
+    (500, 12, 12, 15),
+    (600, 12, 8, 15),
+    (700, 8, 6, 15),
+    (800, 6, 3, 15),
+    (900, 6, 3, 8),
+];
+
+pub fn master(level: usize) -> Timing {
+    let gravity = MASTER_GRAVITY.iter().rev().find(|&&(start, _)| level >= start).unwrap().1;
+    let (_, are, line_clear, lock_delay) = *MASTER_DELAYS.iter().rev().find(|&&(start, ..)| level >= start).unwrap();
+
+    Timing { gravity, are, line_clear, lock_delay }
+}
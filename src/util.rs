@@ -1,5 +1,7 @@
 
-#[derive(Clone, Copy)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Orientation {
     North,
     East,
@@ -36,26 +38,80 @@ impl Orientation {
     }
 }
 
-#[derive(Clone, Copy)]
+impl std::fmt::Display for Orientation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Orientation::North => "north",
+            Orientation::East => "east",
+            Orientation::South => "south",
+            Orientation::West => "west",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub struct UPos2d {
     pub x: usize,
     pub y: usize,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct Pos2d {
     pub x: isize,
     pub y: isize,
 }
 
+/// Player inputs applied to a `GameState`, everything a replay needs besides the frame ticks.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    RotateClockwise,
+    RotateCounterClockwise,
+    SoftDrop,
+    HardDrop,
+    Hold,
+}
+
+impl std::fmt::Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Action::MoveLeft => "left",
+            Action::MoveRight => "right",
+            Action::RotateClockwise => "cw",
+            Action::RotateCounterClockwise => "ccw",
+            Action::SoftDrop => "soft",
+            Action::HardDrop => "hard",
+            Action::Hold => "hold",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Event {
     KeyDown(Keycode),
     KeyUp(Keycode),
+    /// a mouse button pressed or held over a grid cell
+    Mouse(MouseButton, UPos2d),
+    /// a character typed, for entering text
+    Text(char),
     Quit,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Keycode {
-    W, A, S, D, Space, Shift
+    W, A, S, D, Space, Shift,
+    I, O, T, Z, L, J, Tab, Backspace, Return,
+    C, F, Y, P,
+    Left, Right, Up, Down,
+    /// number row, 0 to 9
+    Num(u8),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Right,
 }
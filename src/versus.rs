@@ -0,0 +1,91 @@
+use rand::Rng;
+
+use crate::{bot::Bot, gamestate::{GameState, State}, randomizer::Randomizer};
+
+/// A game against the bot, the attack of every clear is sent to the other field as garbage.
+///
+/// Sent garbage waits until the receiver locks a piece that clears nothing, a clear first cancels
+/// as much waiting garbage as it attacks with and only sends the rest. Both games are dealt the same
+/// pieces, the holes of the garbage rows are random.
+pub struct Versus {
+    /// the bot's game
+    pub opponent: GameState,
+    pub bot: Bot,
+    /// garbage rows waiting to rise into the player's and into the opponent's field
+    pub pending: [usize; 2],
+    /// attack of both games as far as it was traded
+    attack: [usize; 2],
+    /// pieces of both games already checked
+    pieces: [usize; 2],
+}
+
+impl Versus {
+    /// An opponent for `player` in the same mode, dealt the same pieces.
+    pub fn new(player: &GameState, bot: Bot) -> Self {
+        let mut opponent = GameState::new(player.mode, player.visibility);
+        opponent.randomizer = Randomizer::new(player.randomizer.seed);
+
+        // the same start as well, from a board, a fumen or a sequence
+        opponent.sequence = player.sequence.clone();
+        opponent.grid = player.grid;
+        opponent.next = player.next.clone();
+        opponent.hold = player.hold;
+        opponent.current = player.current;
+        opponent.phase = player.phase;
+
+        Self {
+            opponent,
+            bot,
+            pending: [0; 2],
+            attack: [player.stats.attack, 0],
+            pieces: [player.pieces, 0],
+        }
+    }
+
+    /// Runs the bot's game for a frame and trades garbage, to be called once per frame after the player's game advanced.
+    ///
+    /// The game ends for both once one of them tops out, the other one wins.
+    pub fn update(&mut self, player: &mut GameState) {
+        if self.opponent.state == State::Running {
+            self.opponent.tick();
+            for action in self.bot.actions(&self.opponent) {
+                self.opponent.apply(action);
+            }
+        }
+
+        for (i, game) in [&mut *player, &mut self.opponent].into_iter().enumerate() {
+            if game.pieces == self.pieces[i] { continue }
+            self.pieces[i] = game.pieces;
+
+            let attack = game.stats.attack - self.attack[i];
+            self.attack[i] = game.stats.attack;
+
+            let cancelled = attack.min(self.pending[i]);
+            self.pending[i] -= cancelled;
+            self.pending[1 - i] += attack - cancelled;
+
+            let cleared = game.last_lock.as_ref().is_some_and(|lock| lock.lines > 0);
+            if !cleared && self.pending[i] > 0 {
+                game.insert_garbage(self.pending[i], rand::thread_rng().gen_range(0..10));
+                self.pending[i] = 0;
+            }
+        }
+
+        match (player.state, self.opponent.state) {
+            (State::Lost, State::Running) => self.opponent.state = State::Won,
+            (State::Running, State::Lost) => player.state = State::Won,
+            // a game that ran out of pieces waits for the other one, which still tops out or runs out as well
+            _ => {},
+        }
+    }
+
+    /// Whether the game is not over yet, the player's game may already be waiting for the opponent's.
+    pub fn is_running(&self, player: &GameState) -> bool {
+        player.state == State::Running || (player.state == State::Won && self.opponent.state == State::Running)
+    }
+
+    /// Whether neither topped out before both ran out of pieces.
+    pub fn is_draw(&self, player: &GameState) -> bool {
+        player.state == State::Won && self.opponent.state == State::Won
+    }
+}